@@ -0,0 +1,137 @@
+use crate::options::RsyncOptions;
+use crate::profiles::Profiles;
+use crate::utils::{RsyncVersion, flag_supported};
+use crate::{ListFiles, StateMessage, create_rsync_command, run_rsync};
+use clap::Parser;
+use eframe::egui;
+
+/// Options given on the command line. Without --no-gui they only prefill the form.
+#[derive(Parser)]
+#[command(version, about = "A GUI for rsync")]
+pub struct Cli {
+    #[arg(long)]
+    pub src: Option<String>,
+    #[arg(long)]
+    pub dest: Option<String>,
+    #[arg(long)]
+    pub archive: bool,
+    #[arg(long)]
+    pub compress: bool,
+    #[arg(long)]
+    pub delete: bool,
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Run the transfer in the terminal and exit with rsync's exit code.
+    #[arg(long)]
+    pub no_gui: bool,
+    /// Start from a saved profile; the other flags are applied on top of it.
+    #[arg(long)]
+    pub profile: Option<String>,
+}
+
+impl Cli {
+    /// The options to start with: the profile if one was named, plus the flags.
+    pub fn options(&self, profiles: &mut Profiles) -> Result<RsyncOptions, String> {
+        let mut options = match &self.profile {
+            Some(name) => match profiles.get_mut(name) {
+                Some(profile) => profile.options.clone(),
+                None => return Err(format!("No profile named \"{}\"", name)),
+            },
+            None => profiles.defaults.options.clone(),
+        };
+
+        if let Some(src) = &self.src {
+            options.src = src.clone();
+        }
+        if let Some(dest) = &self.dest {
+            options.dest = dest.clone();
+        }
+        options.archive |= self.archive;
+        options.compress |= self.compress;
+        options.delete |= self.delete;
+        options.dry_run |= self.dry_run;
+
+        Ok(options)
+    }
+
+    /// Whether anything beyond --no-gui was given, so the form should be prefilled.
+    pub fn has_options(&self) -> bool {
+        self.src.is_some()
+            || self.dest.is_some()
+            || self.profile.is_some()
+            || self.archive
+            || self.compress
+            || self.delete
+            || self.dry_run
+    }
+}
+
+/// Runs the transfer without a window, printing `[r-synced] <pct>% <speed> <eta>`
+/// lines. Returns the exit code to leave with.
+pub fn run_headless(cli: &Cli) -> i32 {
+    let mut profiles = Profiles::load();
+    for e in &profiles.load_errors {
+        eprintln!("[r-synced] {}", e);
+    }
+    let mut options = match cli.options(&mut profiles) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("[r-synced] {}", e);
+            return 1;
+        }
+    };
+    if options.src.trim().is_empty() || options.dest.trim().is_empty() {
+        eprintln!("[r-synced] --src and --dest are required, either directly or through --profile");
+        return 1;
+    }
+
+    // Without a dry-run there is no file count, so rely on rsync's overall percentage.
+    let Some(version) = RsyncVersion::detect(options.rsync_binary()) else {
+        eprintln!("[r-synced] Could not run {}", options.rsync_binary());
+        return 1;
+    };
+    let version = Some(version);
+    options.overall_progress = flag_supported(version.as_ref(), "--info=progress2");
+
+    let lists = match ListFiles::write(&options) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("[r-synced] {:#}", e);
+            return 1;
+        }
+    };
+    let command = create_rsync_command(&options, lists.paths(), version.as_ref());
+    let (rx, _) = match run_rsync(
+        command,
+        0,
+        0,
+        options.use_pty,
+        None,
+        egui::Context::default(),
+    ) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("[r-synced] Failed to run {}: {}", options.rsync_binary(), e);
+            return 1;
+        }
+    };
+
+    for message in rx {
+        match message {
+            StateMessage::Progress(x) if !x.speed.is_empty() => {
+                println!(
+                    "[r-synced] {:.0}% {} {}",
+                    x.progress * 100.0,
+                    x.speed,
+                    x.time
+                );
+            }
+            StateMessage::Error(x) => eprintln!("{}", x.line),
+            StateMessage::Stats(x) => print!("{}", x),
+            StateMessage::FinishedWithStatus(code) => return code,
+            _ => {}
+        }
+    }
+
+    1
+}
@@ -0,0 +1,146 @@
+use crate::utils::{ItemAction, ItemizedLine, format_bytes, format_count};
+use eframe::egui;
+use eframe::egui::{Color32, RichText};
+
+/// Two trees compared with a dry-run, split by which side differs.
+#[derive(Default)]
+pub struct Comparison {
+    pub only_in_source: Vec<ItemizedLine>,
+    pub only_in_dest: Vec<ItemizedLine>,
+    pub differing: Vec<ItemizedLine>,
+}
+
+fn total_size(items: &[ItemizedLine]) -> u64 {
+    items.iter().map(|x| x.size).sum()
+}
+
+impl Comparison {
+    /// Sorts the itemized lines of `rsync -rin --delete`. New files exist only
+    /// in the source, `*deleting` lines only in the destination, and anything
+    /// else rsync would touch differs. Directories are left out: their files
+    /// are listed on their own.
+    pub fn from_items(items: impl IntoIterator<Item = ItemizedLine>) -> Comparison {
+        let mut comparison = Comparison::default();
+        for item in items {
+            match item.action {
+                ItemAction::New => comparison.only_in_source.push(item),
+                ItemAction::Deleted if !item.path.ends_with('/') => {
+                    comparison.only_in_dest.push(item)
+                }
+                ItemAction::Updated | ItemAction::Other => comparison.differing.push(item),
+                ItemAction::Deleted | ItemAction::Directory => {}
+            }
+        }
+        comparison
+    }
+
+    pub fn in_sync(&self) -> bool {
+        self.only_in_source.is_empty() && self.only_in_dest.is_empty() && self.differing.is_empty()
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        if self.in_sync() {
+            ui.colored_label(
+                Color32::from_rgb(80, 180, 80),
+                "Both locations are in sync.",
+            );
+            return;
+        }
+
+        // rsync reports 0 bytes for deletions, so files only in the
+        // destination are listed without a size.
+        let sections = [
+            (
+                "Only in source",
+                &self.only_in_source,
+                Color32::from_rgb(80, 180, 80),
+                true,
+            ),
+            (
+                "Only in destination",
+                &self.only_in_dest,
+                Color32::from_rgb(220, 80, 80),
+                false,
+            ),
+            (
+                "Differing",
+                &self.differing,
+                Color32::from_rgb(90, 150, 230),
+                true,
+            ),
+        ];
+        for (title, items, color, sized) in sections {
+            let count = format_count(items.len() as u64);
+            let header = if sized {
+                format!("{} ({}, {})", title, count, format_bytes(total_size(items)))
+            } else {
+                format!("{} ({})", title, count)
+            };
+            let header = RichText::new(header).color(color);
+            ui.add_enabled_ui(!items.is_empty(), |ui| {
+                egui::CollapsingHeader::new(header)
+                    .id_salt(("compare", title))
+                    .show(ui, |ui| {
+                        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                        egui::ScrollArea::vertical()
+                            .id_salt(("compare_rows", title))
+                            .max_height(200.0)
+                            .show_rows(ui, row_height, items.len(), |ui, range| {
+                                for item in &items[range] {
+                                    ui.horizontal(|ui| {
+                                        ui.label(&item.path);
+                                        if sized {
+                                            ui.label(RichText::new(format_bytes(item.size)).weak());
+                                        }
+                                    });
+                                }
+                            });
+                    });
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::parse_itemized_line;
+
+    /// From `rsync -rin --delete --out-format="%i %l %n%L"`.
+    const OUTPUT: &str = "\
+sending incremental file list
+cd+++++++++ 4096 photos/
+>f+++++++++ 2048 photos/new.jpg
+cL+++++++++ 11 photos/latest.jpg
+>f.st...... 1000 notes.txt
+.f..t...... 300 todo.txt
+.d..t...... 4096 docs/
+*deleting   0 docs/old.pdf
+*deleting   0 docs/drafts/
+";
+
+    fn paths(items: &[ItemizedLine]) -> Vec<&str> {
+        items.iter().map(|x| x.path.as_str()).collect()
+    }
+
+    #[test]
+    fn sorts_items_by_side() {
+        let comparison = Comparison::from_items(OUTPUT.lines().filter_map(parse_itemized_line));
+
+        assert_eq!(
+            paths(&comparison.only_in_source),
+            ["photos/new.jpg", "photos/latest.jpg"]
+        );
+        assert_eq!(total_size(&comparison.only_in_source), 2059);
+        assert_eq!(paths(&comparison.only_in_dest), ["docs/old.pdf"]);
+        assert_eq!(paths(&comparison.differing), ["notes.txt", "todo.txt"]);
+        assert_eq!(total_size(&comparison.differing), 1300);
+        assert!(!comparison.in_sync());
+    }
+
+    #[test]
+    fn directories_alone_are_in_sync() {
+        let output = ".d..t...... 4096 ./\ncd+++++++++ 4096 empty/\n*deleting   0 gone/\n";
+        assert!(Comparison::from_items(output.lines().filter_map(parse_itemized_line)).in_sync());
+    }
+}
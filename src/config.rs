@@ -0,0 +1,372 @@
+use anyhow::Context;
+use chrono::{Local, NaiveDate};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Layout version written to config.json. Files without one are version 1.
+pub const CONFIG_VERSION: u32 = 2;
+
+const MAX_RECENT: usize = 15;
+const MIN_WINDOW_SIZE: f32 = 300.0;
+
+/// Outer position and inner size of the main window, in logical points.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl WindowGeometry {
+    /// Shrinks the window to fit the monitor it's on and moves it back inside
+    /// that monitor's rect. A window saved on a since-disconnected display
+    /// overlaps none of `monitors` and is moved onto the first, the primary.
+    pub fn clamped(self, monitors: &[egui::Rect]) -> WindowGeometry {
+        let rect = egui::Rect::from_min_size(
+            egui::pos2(self.x, self.y),
+            egui::vec2(self.width, self.height),
+        );
+        let overlap = |monitor: &&egui::Rect| {
+            let x = monitor.intersect(rect);
+            if x.is_positive() { x.area() } else { 0.0 }
+        };
+        let Some(monitor) = monitors
+            .iter()
+            .filter(|x| overlap(x) > 0.0)
+            .max_by(|a, b| overlap(a).total_cmp(&overlap(b)))
+            .or(monitors.first())
+        else {
+            return self;
+        };
+
+        let width = self
+            .width
+            .clamp(MIN_WINDOW_SIZE, monitor.width().max(MIN_WINDOW_SIZE));
+        let height = self
+            .height
+            .clamp(MIN_WINDOW_SIZE, monitor.height().max(MIN_WINDOW_SIZE));
+
+        WindowGeometry {
+            x: self
+                .x
+                .clamp(monitor.min.x, (monitor.max.x - width).max(monitor.min.x)),
+            y: self
+                .y
+                .clamp(monitor.min.y, (monitor.max.y - height).max(monitor.min.y)),
+            width,
+            height,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Theme {
+    System,
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::System, Theme::Light, Theme::Dark];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Theme::System => "Follow system",
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+        }
+    }
+
+    pub fn preference(&self) -> egui::ThemePreference {
+        match self {
+            Theme::System => egui::ThemePreference::System,
+            Theme::Light => egui::ThemePreference::Light,
+            Theme::Dark => egui::ThemePreference::Dark,
+        }
+    }
+}
+
+/// Bytes moved by the runs of one day, for keeping an eye on metered connections.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct DailyUsage {
+    pub date: Option<NaiveDate>,
+    pub bytes: u64,
+}
+
+impl DailyUsage {
+    /// The count so far today; one left over from an earlier day reads as zero.
+    pub fn today(&self) -> u64 {
+        if self.date == Some(Local::now().date_naive()) {
+            self.bytes
+        } else {
+            0
+        }
+    }
+
+    /// Counts `bytes` towards today, starting over on the first run after midnight.
+    pub fn add(&mut self, bytes: u64) {
+        let today = Local::now().date_naive();
+        if self.date != Some(today) {
+            self.date = Some(today);
+            self.bytes = 0;
+        }
+        self.bytes += bytes;
+    }
+}
+
+/// App-level state that survives restarts but isn't part of a transfer.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub version: u32,
+    /// Written by a newer release; loaded as far as it can be, but never saved over.
+    #[serde(skip)]
+    pub read_only: bool,
+    pub recent_sources: Vec<String>,
+    pub recent_dests: Vec<String>,
+    pub window: Option<WindowGeometry>,
+    pub advanced_open: bool,
+    /// Tee everything rsync prints into a file under the data directory.
+    pub write_raw_logs: bool,
+    /// Play a sound and ask for attention when a run ends.
+    pub completion_cue: bool,
+    /// Runs shorter than this end silently.
+    pub completion_cue_min_secs: u32,
+    pub theme: Theme,
+    /// Points per pixel for the whole interface.
+    pub ui_scale: f32,
+    /// Older raw output lines are dropped from the progress window past this point.
+    pub max_raw_lines: usize,
+    /// Presets whose confirmation has been accepted, so it isn't asked again.
+    pub confirmed_presets: Vec<String>,
+    pub usage: DailyUsage,
+    /// Skip the rest of the scheduled runs that are due once one of them fails.
+    pub schedule_stop_on_failure: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            read_only: false,
+            recent_sources: Vec::new(),
+            recent_dests: Vec::new(),
+            window: None,
+            advanced_open: false,
+            write_raw_logs: false,
+            completion_cue: false,
+            completion_cue_min_secs: 10,
+            theme: Theme::System,
+            ui_scale: 1.2,
+            max_raw_lines: 5000,
+            confirmed_presets: Vec::new(),
+            usage: DailyUsage::default(),
+            schedule_stop_on_failure: false,
+        }
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|x| x.join("r-synced").join("config.json"))
+}
+
+fn version_of(value: &Value) -> u32 {
+    value
+        .get("version")
+        .and_then(Value::as_u64)
+        .map_or(1, |x| x as u32)
+}
+
+/// Upgrades a config of any older layout one version at a time.
+pub fn migrate(mut old: Value) -> Config {
+    for version in version_of(&old)..CONFIG_VERSION {
+        match version {
+            // Version 1 had no version field; every other field kept its meaning.
+            1 => {
+                if let Some(fields) = old.as_object_mut() {
+                    fields.insert("version".to_string(), Value::from(2));
+                }
+            }
+            _ => unreachable!("no migration from config version {}", version),
+        }
+    }
+
+    let mut config: Config = serde_json::from_value(old).unwrap_or_default();
+    config.version = CONFIG_VERSION;
+    config
+}
+
+/// Keeps the file as it was before a migration, next to it.
+fn backup(path: &Path, version: u32) -> anyhow::Result<()> {
+    let backup = path.with_extension(format!("json.v{}.bak", version));
+    fs::copy(path, &backup).with_context(|| format!("Failed to back up {}", path.display()))?;
+    Ok(())
+}
+
+impl Config {
+    pub fn load() -> Config {
+        let Some(path) = config_path() else {
+            return Config::default();
+        };
+        let Some(value) = fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str::<Value>(&data).ok())
+        else {
+            return Config::default();
+        };
+
+        let version = version_of(&value);
+        if version > CONFIG_VERSION {
+            let mut config: Config = serde_json::from_value(value).unwrap_or_default();
+            config.read_only = true;
+            return config;
+        }
+
+        let config = migrate(value);
+        // Without a backup the old file stays as it is and is migrated again next time.
+        if version < CONFIG_VERSION && backup(&path, version).is_ok() {
+            let _ = config.save();
+        }
+        config
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+
+        let path = config_path().context("Could not determine the config directory")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create the config directory")?;
+        }
+
+        let data = serde_json::to_string_pretty(self)?;
+        fs::write(&path, data).context("Failed to write config")?;
+
+        Ok(())
+    }
+
+    pub fn remember_paths(&mut self, src: &str, dest: &str) {
+        push_recent(&mut self.recent_sources, src);
+        push_recent(&mut self.recent_dests, dest);
+    }
+}
+
+fn push_recent(list: &mut Vec<String>, value: &str) {
+    if value.is_empty() {
+        return;
+    }
+
+    list.retain(|x| x != value);
+    list.insert(0, value.to_string());
+    list.truncate(MAX_RECENT);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Written before configs had a version field.
+    const V1: &str = r#"{
+        "recent_sources": ["/home/me/photos/"],
+        "recent_dests": ["backup:photos"],
+        "window": { "x": 10.0, "y": 20.0, "width": 800.0, "height": 600.0 },
+        "advanced_open": true,
+        "theme": "Dark"
+    }"#;
+
+    const V2: &str = r#"{
+        "version": 2,
+        "recent_sources": ["/srv/data/"],
+        "recent_dests": [],
+        "write_raw_logs": true,
+        "completion_cue": true,
+        "completion_cue_min_secs": 30,
+        "theme": "Light",
+        "ui_scale": 1.5,
+        "max_raw_lines": 200
+    }"#;
+
+    #[test]
+    fn migrates_v1() {
+        let config = migrate(serde_json::from_str(V1).unwrap());
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.recent_sources, ["/home/me/photos/"]);
+        assert_eq!(config.recent_dests, ["backup:photos"]);
+        assert!(
+            config.window
+                == Some(WindowGeometry {
+                    x: 10.0,
+                    y: 20.0,
+                    width: 800.0,
+                    height: 600.0
+                })
+        );
+        assert!(config.advanced_open);
+        assert!(config.theme == Theme::Dark);
+        // Fields it didn't have yet start at their defaults.
+        assert_eq!(config.max_raw_lines, Config::default().max_raw_lines);
+        assert!(!config.read_only);
+    }
+
+    #[test]
+    fn loads_v2_as_is() {
+        let value: Value = serde_json::from_str(V2).unwrap();
+        assert_eq!(version_of(&value), 2);
+        let config = migrate(value);
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.recent_sources, ["/srv/data/"]);
+        assert!(config.write_raw_logs);
+        assert!(config.completion_cue);
+        assert_eq!(config.completion_cue_min_secs, 30);
+        assert!(config.theme == Theme::Light);
+        assert_eq!(config.ui_scale, 1.5);
+        assert_eq!(config.max_raw_lines, 200);
+    }
+
+    fn window(x: f32, y: f32, width: f32, height: f32) -> WindowGeometry {
+        WindowGeometry {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// A 1920x1080 primary with a 2560x1440 display to its right.
+    fn monitors() -> [egui::Rect; 2] {
+        [
+            egui::Rect::from_min_size(egui::pos2(0.0, 0.0), egui::vec2(1920.0, 1080.0)),
+            egui::Rect::from_min_size(egui::pos2(1920.0, 0.0), egui::vec2(2560.0, 1440.0)),
+        ]
+    }
+
+    #[test]
+    fn keeps_a_window_on_the_second_monitor() {
+        let on_second = window(2500.0, 300.0, 1200.0, 900.0);
+        assert!(on_second.clamped(&monitors()) == on_second);
+    }
+
+    #[test]
+    fn clamps_into_the_monitor_the_window_is_on() {
+        // Hangs off the right edge of the second monitor.
+        let clamped = window(4000.0, 1200.0, 800.0, 600.0).clamped(&monitors());
+        assert!(clamped == window(3680.0, 840.0, 800.0, 600.0));
+    }
+
+    #[test]
+    fn moves_a_window_from_a_disconnected_display_to_the_primary() {
+        let clamped = window(5000.0, -900.0, 2400.0, 600.0).clamped(&monitors());
+        assert!(clamped == window(0.0, 0.0, 1920.0, 600.0));
+    }
+
+    #[test]
+    fn leaves_the_window_alone_without_monitors() {
+        let far = window(5000.0, 5000.0, 800.0, 600.0);
+        assert!(far.clamped(&[]) == far);
+    }
+}
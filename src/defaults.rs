@@ -0,0 +1,168 @@
+use crate::options::RsyncOptions;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::PathBuf;
+
+/// Never inherited: every profile has its own.
+const PATHS: [&str; 2] = ["src", "dest"];
+
+/// Options every new profile starts from, and that profiles follow wherever
+/// they don't set a value of their own. Same shape as the form, with the
+/// paths left empty.
+#[derive(Default, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct GlobalDefaults {
+    pub options: RsyncOptions,
+}
+
+fn defaults_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|x| x.join("r-synced").join("defaults.toml"))
+}
+
+fn to_map(options: &RsyncOptions) -> Map<String, Value> {
+    match serde_json::to_value(options) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+impl GlobalDefaults {
+    /// Takes `options` as the new defaults, without their paths.
+    pub fn from_options(options: &RsyncOptions) -> GlobalDefaults {
+        GlobalDefaults {
+            options: RsyncOptions {
+                src: String::new(),
+                dest: String::new(),
+                ..options.clone()
+            },
+        }
+    }
+
+    pub fn load() -> GlobalDefaults {
+        defaults_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|data| toml::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = defaults_path().context("Could not determine the config directory")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create the config directory")?;
+        }
+        let data = toml::to_string_pretty(self).context("Failed to serialize the defaults")?;
+        fs::write(&path, data).with_context(|| format!("Failed to write {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// The values in `options` that differ from these defaults, by option name.
+    pub fn deviations(&self, options: &RsyncOptions) -> Map<String, Value> {
+        let defaults = to_map(&self.options);
+        to_map(options)
+            .into_iter()
+            .filter(|(key, value)| defaults.get(key) != Some(value))
+            .collect()
+    }
+
+    /// Names of the options in `options` that differ from these defaults, leaving out the paths.
+    pub fn overridden(&self, options: &RsyncOptions) -> Vec<String> {
+        self.deviations(options)
+            .into_iter()
+            .map(|(key, _)| key)
+            .filter(|key| !PATHS.contains(&key.as_str()))
+            .collect()
+    }
+
+    /// These defaults with `deviations` laid over them. A value that no longer
+    /// fits its option, e.g. after the option changed type, is left at the default.
+    pub fn merge(&self, deviations: &Map<String, Value>) -> RsyncOptions {
+        let mut merged = to_map(&self.options);
+        for (key, value) in deviations {
+            let previous = merged.insert(key.clone(), value.clone());
+            if serde_json::from_value::<RsyncOptions>(Value::Object(merged.clone())).is_err() {
+                match previous {
+                    Some(previous) => merged.insert(key.clone(), previous),
+                    None => merged.remove(key),
+                };
+            }
+        }
+
+        serde_json::from_value(Value::Object(merged)).unwrap_or_else(|_| self.options.clone())
+    }
+
+    /// Puts the default value of the option `key` back into `options`.
+    pub fn reset(&self, options: &mut RsyncOptions, key: &str) {
+        let mut deviations = self.deviations(options);
+        deviations.remove(key);
+        *options = self.merge(&deviations);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> GlobalDefaults {
+        GlobalDefaults::from_options(&RsyncOptions {
+            src: "/home/me/".to_string(),
+            compress: true,
+            bwlimit_kbps: 500,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn lists_deviations_and_merges_them_back() {
+        let defaults = defaults();
+        assert!(defaults.options.src.is_empty());
+
+        let options = RsyncOptions {
+            src: "/srv/data/".to_string(),
+            compress: false,
+            checksum: true,
+            ..defaults.options.clone()
+        };
+        let deviations = defaults.deviations(&options);
+        let mut keys: Vec<_> = deviations.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["checksum", "compress", "src"]);
+        let mut overridden = defaults.overridden(&options);
+        overridden.sort();
+        assert_eq!(overridden, ["checksum", "compress"]);
+
+        let merged = defaults.merge(&deviations);
+        assert_eq!(merged.src, "/srv/data/");
+        assert!(!merged.compress);
+        assert!(merged.checksum);
+        assert_eq!(merged.bwlimit_kbps, 500);
+    }
+
+    #[test]
+    fn keeps_the_default_for_values_that_no_longer_fit() {
+        let defaults = defaults();
+        let mut deviations = Map::new();
+        deviations.insert("bwlimit_kbps".to_string(), Value::from("fast"));
+        deviations.insert("compress".to_string(), Value::from(false));
+        deviations.insert("no_such_option".to_string(), Value::from(1));
+
+        let merged = defaults.merge(&deviations);
+        assert_eq!(merged.bwlimit_kbps, 500);
+        assert!(!merged.compress);
+    }
+
+    #[test]
+    fn resets_one_option() {
+        let defaults = defaults();
+        let mut options = RsyncOptions {
+            compress: false,
+            bwlimit_kbps: 100,
+            ..defaults.options.clone()
+        };
+        defaults.reset(&mut options, "bwlimit_kbps");
+        assert_eq!(options.bwlimit_kbps, 500);
+        assert!(!options.compress);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::utils::{ItemAction, ItemizedLine, format_bytes};
+use eframe::egui;
+use eframe::egui::{Color32, RichText};
+use std::collections::BTreeMap;
+
+/// Sections of the preview, in display order.
+const SECTIONS: [(ItemAction, &str, Color32); 4] = [
+    (ItemAction::New, "New", Color32::from_rgb(80, 180, 80)),
+    (
+        ItemAction::Updated,
+        "Changed",
+        Color32::from_rgb(90, 150, 230),
+    ),
+    (
+        ItemAction::Deleted,
+        "Deleted",
+        Color32::from_rgb(220, 80, 80),
+    ),
+    (ItemAction::Other, "Attributes only", Color32::GRAY),
+];
+
+/// What a dry-run would change, grouped by action and then by directory.
+pub struct DiffPreview {
+    /// One map per entry of `SECTIONS`, from directory to (file name, size).
+    sections: Vec<BTreeMap<String, Vec<(String, u64)>>>,
+    counts: Vec<usize>,
+}
+
+impl DiffPreview {
+    pub fn new(items: impl IntoIterator<Item = ItemizedLine>) -> DiffPreview {
+        let mut sections = vec![BTreeMap::new(); SECTIONS.len()];
+        let mut counts = vec![0; SECTIONS.len()];
+
+        for item in items {
+            // Directories show up as the groups themselves.
+            let Some(section) = SECTIONS.iter().position(|(x, _, _)| *x == item.action) else {
+                continue;
+            };
+
+            let path = item.path.trim_end_matches('/');
+            let (dir, name) = path.rsplit_once('/').unwrap_or((".", path));
+            sections[section]
+                .entry(dir.to_string())
+                .or_insert_with(Vec::new)
+                .push((name.to_string(), item.size));
+            counts[section] += 1;
+        }
+
+        DiffPreview { sections, counts }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.iter().all(|&x| x == 0)
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        if self.is_empty() {
+            ui.label("Source and destination are already in sync.");
+            return;
+        }
+
+        for (i, (_, title, color)) in SECTIONS.iter().enumerate() {
+            if self.counts[i] == 0 {
+                continue;
+            }
+
+            let header = RichText::new(format!("{} ({})", title, self.counts[i])).color(*color);
+            egui::CollapsingHeader::new(header)
+                .id_salt(("diff_section", i))
+                .default_open(i < 3)
+                .show(ui, |ui| {
+                    for (dir, files) in &self.sections[i] {
+                        egui::CollapsingHeader::new(format!("{}/ ({})", dir, files.len()))
+                            .id_salt(("diff_dir", i, dir))
+                            .show(ui, |ui| {
+                                for (name, size) in files {
+                                    ui.horizontal(|ui| {
+                                        ui.label(name);
+                                        ui.label(RichText::new(format_bytes(*size)).weak());
+                                    });
+                                }
+                            });
+                    }
+                });
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use crate::options::RsyncOptions;
+use anyhow::Context;
+use std::fs;
+use std::path::PathBuf;
+
+/// The form as last edited, written shortly after the user stops typing so a
+/// crashed or killed session can be restored. Removed again on a clean exit.
+/// Lives in the cache directory since losing it only costs the offer to restore.
+fn draft_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|x| x.join("r-synced").join("session.json"))
+}
+
+/// Where drafts were kept before they moved to the cache directory.
+fn legacy_draft_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|x| x.join("r-synced").join("draft.json"))
+}
+
+pub fn load() -> Option<RsyncOptions> {
+    if let Some(legacy) = legacy_draft_path()
+        && let Some(path) = draft_path()
+        && legacy.exists()
+        && !path.exists()
+        && let Some(parent) = path.parent()
+        && fs::create_dir_all(parent).is_ok()
+    {
+        let _ = fs::rename(&legacy, &path)
+            .or_else(|_| fs::copy(&legacy, &path).and_then(|_| fs::remove_file(&legacy)));
+    }
+
+    draft_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|data| serde_json::from_str(&data).ok())
+}
+
+pub fn save(options: &RsyncOptions) -> anyhow::Result<()> {
+    let path = draft_path().context("Could not determine the cache directory")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("Failed to create the cache directory")?;
+    }
+
+    let data = serde_json::to_string(options)?;
+    fs::write(&path, data).context("Failed to write the session file")?;
+
+    Ok(())
+}
+
+pub fn clear() {
+    if let Some(path) = draft_path() {
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::utils::{RsyncVersion, find_in_path};
+use std::env;
+use std::process::{Command, Stdio};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Failed,
+}
+
+impl CheckStatus {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            CheckStatus::Ok => "✔",
+            CheckStatus::Warning => "⚠",
+            CheckStatus::Failed => "✖",
+        }
+    }
+}
+
+/// Result of one startup check, with a hint on how to fix it when it didn't pass.
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub hint: Option<&'static str>,
+}
+
+impl Check {
+    fn new(name: &'static str, status: CheckStatus, detail: String, hint: &'static str) -> Check {
+        Check {
+            name,
+            status,
+            detail,
+            hint: (status != CheckStatus::Ok).then_some(hint),
+        }
+    }
+}
+
+const KEY_FILES: [&str; 4] = ["id_ed25519", "id_ecdsa", "id_rsa", "id_ed25519_sk"];
+
+/// Checks the tools remote transfers depend on. Runs external programs, so
+/// call it off the UI thread. Only the rsync check matters for local transfers.
+pub fn run_checks(rsync_binary: &str) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    checks.push(match RsyncVersion::detect(rsync_binary) {
+        Some(version) => Check::new(
+            "rsync",
+            CheckStatus::Ok,
+            format!("{}.{}.{} (protocol {})", version.major, version.minor, version.patch, version.protocol),
+            "",
+        ),
+        None => Check::new(
+            "rsync",
+            CheckStatus::Failed,
+            format!("{} could not be run", rsync_binary),
+            "Install rsync, or set its full path under Advanced. Apps started from the desktop may see a shorter PATH than your shell.",
+        ),
+    });
+
+    checks.push(match find_in_path("ssh") {
+        Some(path) => Check::new("ssh", CheckStatus::Ok, path.display().to_string(), ""),
+        None => Check::new(
+            "ssh",
+            CheckStatus::Warning,
+            "not found in PATH".to_string(),
+            "Install the OpenSSH client; it is only needed for remote transfers.",
+        ),
+    });
+
+    checks.push(match env::var_os("SSH_AUTH_SOCK") {
+        None => Check::new(
+            "SSH agent",
+            CheckStatus::Warning,
+            "SSH_AUTH_SOCK is not set".to_string(),
+            "Start ssh-agent from your session, or use keys without a passphrase.",
+        ),
+        Some(_) => {
+            let status = Command::new("ssh-add")
+                .arg("-l")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+            // ssh-add -l exits with 1 when the agent holds no keys and 2 when it can't be reached.
+            match status.ok().and_then(|x| x.code()) {
+                Some(0) => Check::new(
+                    "SSH agent",
+                    CheckStatus::Ok,
+                    "running with keys loaded".to_string(),
+                    "",
+                ),
+                Some(1) => Check::new(
+                    "SSH agent",
+                    CheckStatus::Warning,
+                    "running, but no keys are loaded".to_string(),
+                    "Add your key with ssh-add.",
+                ),
+                _ => Check::new(
+                    "SSH agent",
+                    CheckStatus::Warning,
+                    "not answering".to_string(),
+                    "SSH_AUTH_SOCK points to an agent that isn't running; restart ssh-agent.",
+                ),
+            }
+        }
+    });
+
+    let ssh_dir = dirs::home_dir().map(|x| x.join(".ssh"));
+    let keys: Vec<_> = KEY_FILES
+        .iter()
+        .filter(|x| ssh_dir.as_ref().is_some_and(|dir| dir.join(x).is_file()))
+        .copied()
+        .collect();
+    checks.push(if keys.is_empty() {
+        Check::new(
+            "SSH keys",
+            CheckStatus::Warning,
+            "no default key in ~/.ssh".to_string(),
+            "Create one with ssh-keygen and copy it to the server with ssh-copy-id.",
+        )
+    } else {
+        Check::new("SSH keys", CheckStatus::Ok, keys.join(", "), "")
+    });
+
+    checks
+}
@@ -0,0 +1,251 @@
+use crate::utils::format_count;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Older entries are dropped past this point.
+const MAX_ENTRIES: usize = 1000;
+/// Problems tracked for the summary; the least repeated one makes room for a new one.
+const MAX_GROUPS: usize = 1000;
+
+struct Entry {
+    line: String,
+    key: String,
+    count: u64,
+}
+
+/// Lines that only differ in the paths they mention, over the whole run.
+struct Group {
+    count: u64,
+    reason: String,
+    base: Option<PathBuf>,
+}
+
+/// The error panel's lines. Consecutive lines that only differ in a quoted
+/// path are stored once with a counter, so a permission problem on a large
+/// tree doesn't turn into thousands of lines.
+#[derive(Default)]
+pub struct ErrorLog {
+    entries: VecDeque<Entry>,
+    dropped: u64,
+    groups: HashMap<String, Group>,
+    /// Text pushed since the last newline.
+    partial: String,
+}
+
+lazy_static! {
+    static ref RE_QUOTED: Regex = Regex::new(r#""[^"]*""#).unwrap();
+    static ref RE_ERRNO: Regex = Regex::new(r"\s*\(\d+\)$").unwrap();
+}
+
+/// The line with its quoted paths blanked out, so lines about different files
+/// but the same problem compare equal.
+fn group_key(line: &str) -> String {
+    RE_QUOTED.replace_all(line.trim(), "\"…\"").to_string()
+}
+
+/// What went wrong, e.g. "Permission denied" from
+/// `rsync: opendir "/srv/x" failed: Permission denied (13)`.
+fn reason(line: &str) -> &str {
+    let reason = line.rsplit_once(": ").map_or(line, |(_, x)| x);
+    let end = RE_ERRNO.find(reason).map_or(reason.len(), |x| x.start());
+    reason[..end].trim()
+}
+
+fn quoted_path(line: &str) -> Option<&Path> {
+    RE_QUOTED
+        .find(line)
+        .map(|x| Path::new(x.as_str().trim_matches('"')))
+}
+
+impl ErrorLog {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.partial.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        *self = ErrorLog::default();
+    }
+
+    pub fn push(&mut self, c: char) {
+        let mut buffer = [0; 4];
+        self.push_str(c.encode_utf8(&mut buffer));
+    }
+
+    pub fn push_str(&mut self, text: &str) {
+        self.partial.push_str(text);
+        while let Some(end) = self.partial.find('\n') {
+            let line = self.partial[..end].to_string();
+            self.partial.drain(..=end);
+            self.push_line(&line);
+        }
+    }
+
+    fn push_line(&mut self, line: &str) {
+        if line.trim().is_empty() {
+            return;
+        }
+
+        let key = group_key(line);
+        let path = quoted_path(line);
+        if self.groups.len() == MAX_GROUPS
+            && !self.groups.contains_key(&key)
+            && let Some(smallest) = self
+                .groups
+                .iter()
+                .min_by_key(|(_, x)| x.count)
+                .map(|(x, _)| x.clone())
+        {
+            self.groups.remove(&smallest);
+        }
+        let group = self.groups.entry(key.clone()).or_insert_with(|| Group {
+            count: 0,
+            reason: reason(line).to_string(),
+            base: path.and_then(Path::parent).map(Path::to_path_buf),
+        });
+        group.count += 1;
+        if let (Some(base), Some(path)) = (&mut group.base, path) {
+            while !path.starts_with(&*base) && base.pop() {}
+        }
+
+        if let Some(last) = self.entries.back_mut()
+            && last.key == key
+        {
+            last.count += 1;
+            return;
+        }
+
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(Entry {
+            line: line.to_string(),
+            key,
+            count: 1,
+        });
+    }
+
+    /// The most repeated problem, e.g. "Permission denied on 50,312 paths
+    /// under /srv/media", once one has come up more than once.
+    pub fn summary(&self) -> Option<String> {
+        let group = self
+            .groups
+            .values()
+            .filter(|x| x.count > 1)
+            .max_by_key(|x| x.count)?;
+        let mut summary = format!("{} on {} paths", group.reason, format_count(group.count));
+        if let Some(base) = group.base.as_ref().filter(|x| !x.as_os_str().is_empty()) {
+            summary.push_str(&format!(" under {}", base.display()));
+        }
+        Some(summary)
+    }
+}
+
+impl fmt::Display for ErrorLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.dropped > 0 {
+            writeln!(
+                f,
+                "({} earlier errors not shown)",
+                format_count(self.dropped)
+            )?;
+        }
+        for entry in &self.entries {
+            if entry.count > 1 {
+                writeln!(f, "{} ×{}", entry.line, format_count(entry.count))?;
+            } else {
+                writeln!(f, "{}", entry.line)?;
+            }
+        }
+        write!(f, "{}", self.partial)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_lines_that_only_differ_in_paths() {
+        let mut log = ErrorLog::default();
+        for name in ["a", "b", "c"] {
+            log.push_str(&format!(
+                "rsync: opendir \"/srv/media/{name}\" failed: Permission denied (13)\n"
+            ));
+        }
+        log.push_str(
+            "rsync: send_files failed to open \"/srv/other/x\": No such file or directory (2)\n",
+        );
+        log.push_str("rsync: opendir \"/srv/media/d/e\" failed: Permission denied (13)\n");
+
+        assert_eq!(
+            log.to_string(),
+            "rsync: opendir \"/srv/media/a\" failed: Permission denied (13) ×3\n\
+             rsync: send_files failed to open \"/srv/other/x\": No such file or directory (2)\n\
+             rsync: opendir \"/srv/media/d/e\" failed: Permission denied (13)\n"
+        );
+        assert_eq!(
+            log.summary().as_deref(),
+            Some("Permission denied on 4 paths under /srv/media")
+        );
+    }
+
+    #[test]
+    fn summarizes_only_repeated_problems() {
+        let mut log = ErrorLog::default();
+        log.push_str("rsync: opendir \"a\" failed: Permission denied (13)\n");
+        assert_eq!(log.summary(), None);
+
+        // Relative paths with nothing in common leave no base to name.
+        log.push_str("rsync: opendir \"b\" failed: Permission denied (13)\n");
+        assert_eq!(
+            log.summary().as_deref(),
+            Some("Permission denied on 2 paths")
+        );
+    }
+
+    #[test]
+    fn keeps_partial_lines_until_the_newline() {
+        let mut log = ErrorLog::default();
+        log.push_str("rsync error: some files");
+        assert!(!log.is_empty());
+        assert_eq!(log.to_string(), "rsync error: some files");
+        log.push_str(" could not be transferred (code 23)\n\n");
+        assert_eq!(
+            log.to_string(),
+            "rsync error: some files could not be transferred (code 23)\n"
+        );
+        log.clear();
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn drops_the_oldest_entries_past_the_limit() {
+        let mut log = ErrorLog::default();
+        for i in 0..MAX_ENTRIES + 2 {
+            log.push_str(&format!("error {i}\n"));
+        }
+        let text = log.to_string();
+        assert!(text.starts_with("(2 earlier errors not shown)\nerror 2\n"));
+        assert!(text.ends_with(&format!("error {}\n", MAX_ENTRIES + 1)));
+    }
+
+    #[test]
+    fn evicts_the_least_repeated_groups_past_the_limit() {
+        let mut log = ErrorLog::default();
+        for _ in 0..3 {
+            log.push_str("rsync: opendir \"/srv/a\" failed: Permission denied (13)\n");
+        }
+        for i in 0..MAX_GROUPS * 2 {
+            log.push_str(&format!("error {i}\n"));
+        }
+        assert_eq!(log.groups.len(), MAX_GROUPS);
+        assert_eq!(
+            log.summary().as_deref(),
+            Some("Permission denied on 3 paths under /srv")
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use crate::utils::{ItemAction, ItemizedLine, format_bytes};
+use eframe::egui;
+use eframe::egui::{Color32, RichText};
+
+#[derive(Default, Clone, Copy, PartialEq)]
+enum Column {
+    #[default]
+    Index,
+    Action,
+    Path,
+    Size,
+}
+
+/// Actions that get a filter chip above the table, in display order.
+const CHIPS: [(ItemAction, &str); 4] = [
+    (ItemAction::New, "New"),
+    (ItemAction::Updated, "Updated"),
+    (ItemAction::Deleted, "Deleted"),
+    (ItemAction::Directory, "Directories"),
+];
+
+fn chip_index(action: ItemAction) -> Option<usize> {
+    CHIPS.iter().position(|(x, _)| *x == action)
+}
+
+struct FileEvent {
+    index: usize,
+    item: ItemizedLine,
+    /// Lowercased extension without the dot, computed once on ingest so the
+    /// filter stays cheap on large transfers.
+    extension: String,
+}
+
+fn extension_of(path: &str) -> String {
+    let name = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(path);
+    match name.rfind('.') {
+        Some(i) if i > 0 => name[i + 1..].to_lowercase(),
+        _ => String::new(),
+    }
+}
+
+/// Itemized file events of the current transfer, shown as a sortable table.
+#[derive(Default)]
+pub struct FileLog {
+    events: Vec<FileEvent>,
+    order: Vec<usize>,
+    /// `order` with the filters applied; this is what gets rendered.
+    visible: Vec<usize>,
+    sort_by: Column,
+    descending: bool,
+    dirty: bool,
+
+    counts: [usize; CHIPS.len()],
+    /// When no chip is selected every action is shown.
+    selected: [bool; CHIPS.len()],
+    extension_filter: String,
+    extensions: Vec<String>,
+}
+
+impl FileLog {
+    pub fn clear(&mut self) {
+        self.events.clear();
+        self.order.clear();
+        self.visible.clear();
+        self.counts = Default::default();
+        self.dirty = false;
+    }
+
+    pub fn push(&mut self, item: ItemizedLine) {
+        if let Some(chip) = chip_index(item.action) {
+            self.counts[chip] += 1;
+        }
+
+        let index = self.events.len();
+        self.events.push(FileEvent {
+            index: index + 1,
+            extension: extension_of(&item.path),
+            item,
+        });
+        self.order.push(index);
+
+        if self.sort_by != Column::Index || self.descending {
+            self.dirty = true;
+        } else if !self.dirty && self.matches(&self.events[index]) {
+            self.visible.push(index);
+        }
+    }
+
+    fn matches(&self, event: &FileEvent) -> bool {
+        let action_shown = !self.selected.contains(&true)
+            || chip_index(event.item.action).is_some_and(|chip| self.selected[chip]);
+        let extension_shown =
+            self.extensions.is_empty() || self.extensions.contains(&event.extension);
+
+        action_shown && extension_shown
+    }
+
+    fn apply_filters(&mut self) {
+        let visible: Vec<usize> = self
+            .order
+            .iter()
+            .copied()
+            .filter(|&i| self.matches(&self.events[i]))
+            .collect();
+        self.visible = visible;
+    }
+
+    fn sort(&mut self) {
+        let events = &self.events;
+        match self.sort_by {
+            Column::Index => self.order.sort_by_key(|&i| events[i].index),
+            Column::Action => self.order.sort_by_key(|&i| events[i].item.action.badge()),
+            Column::Path => self
+                .order
+                .sort_by(|&a, &b| events[a].item.path.cmp(&events[b].item.path)),
+            Column::Size => self.order.sort_by_key(|&i| events[i].item.size),
+        }
+        if self.descending {
+            self.order.reverse();
+        }
+        self.apply_filters();
+        self.dirty = false;
+    }
+
+    fn filters_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            for (chip, (action, title)) in CHIPS.iter().enumerate() {
+                let text = RichText::new(format!("{} {}", title, self.counts[chip]))
+                    .color(action_color(*action));
+                if ui.toggle_value(&mut self.selected[chip], text).changed() {
+                    self.dirty = true;
+                }
+            }
+
+            ui.separator();
+            ui.label("Extensions:");
+            let edit = egui::TextEdit::singleline(&mut self.extension_filter)
+                .hint_text(".jpg,.png")
+                .desired_width(120.0);
+            if ui.add(edit).changed() {
+                self.extensions = self
+                    .extension_filter
+                    .split(',')
+                    .map(|x| x.trim().trim_start_matches('.').to_lowercase())
+                    .filter(|x| !x.is_empty())
+                    .collect();
+                self.dirty = true;
+            }
+        });
+    }
+
+    fn header(&mut self, ui: &mut egui::Ui, column: Column, title: &str, width: f32) {
+        let arrow = match (self.sort_by == column, self.descending) {
+            (true, false) => " ⏶",
+            (true, true) => " ⏷",
+            _ => "",
+        };
+
+        let button =
+            egui::Button::new(RichText::new(format!("{}{}", title, arrow)).strong()).frame(false);
+        if ui.add_sized([width, 18.0], button).clicked() {
+            if self.sort_by == column {
+                self.descending = !self.descending;
+            } else {
+                self.sort_by = column;
+                self.descending = false;
+            }
+            self.dirty = true;
+        }
+    }
+
+    /// Regular files that were sent, leaving out directories and links.
+    pub fn transferred_files(&self) -> impl Iterator<Item = &str> {
+        self.events
+            .iter()
+            .filter(|x| matches!(x.item.action, ItemAction::New | ItemAction::Updated))
+            .map(|x| x.item.path.as_str())
+            .filter(|x| !x.ends_with('/') && !x.contains(" -> ") && !x.contains(" => "))
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        self.filters_ui(ui);
+
+        if self.dirty {
+            self.sort();
+        }
+
+        ui.horizontal(|ui| {
+            self.header(ui, Column::Index, "#", 50.0);
+            self.header(ui, Column::Action, "Action", 50.0);
+            self.header(ui, Column::Size, "Size", 70.0);
+            self.header(ui, Column::Path, "Path", 200.0);
+        });
+
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::vertical()
+            .id_salt("file_log_table")
+            .stick_to_bottom(self.sort_by == Column::Index && !self.descending)
+            .auto_shrink([false; 2])
+            .max_height(100.0)
+            .show_rows(ui, row_height, self.visible.len(), |ui, range| {
+                for &i in &self.visible[range] {
+                    let event = &self.events[i];
+                    ui.horizontal(|ui| {
+                        ui.add_sized(
+                            [50.0, row_height],
+                            egui::Label::new(event.index.to_string()),
+                        );
+                        ui.add_sized(
+                            [50.0, row_height],
+                            egui::Label::new(
+                                RichText::new(event.item.action.badge())
+                                    .monospace()
+                                    .color(action_color(event.item.action)),
+                            ),
+                        );
+                        ui.add_sized(
+                            [70.0, row_height],
+                            egui::Label::new(format_bytes(event.item.size)),
+                        );
+                        ui.add(egui::Label::new(&event.item.path).truncate());
+                    });
+                }
+            });
+    }
+}
+
+fn action_color(action: ItemAction) -> Color32 {
+    match action {
+        ItemAction::New => Color32::from_rgb(80, 180, 80),
+        ItemAction::Updated => Color32::from_rgb(90, 150, 230),
+        ItemAction::Deleted => Color32::from_rgb(220, 80, 80),
+        ItemAction::Directory | ItemAction::Other => Color32::GRAY,
+    }
+}
@@ -0,0 +1,167 @@
+use crate::options::RsyncOptions;
+use std::path::Path;
+
+/// Filesystems that can't keep some of the metadata rsync preserves. rsync
+/// reports an error for every file it fails to set it on.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Filesystem {
+    Fat,
+    ExFat,
+    Ntfs,
+    Cifs,
+}
+
+impl Filesystem {
+    /// From the `f_type` magic number Linux reports in statfs.
+    pub fn from_magic(magic: u32) -> Option<Filesystem> {
+        match magic {
+            0x4d44 => Some(Filesystem::Fat),
+            0x2011_bab0 => Some(Filesystem::ExFat),
+            // ntfs3, then the older read-only driver.
+            0x7366_746e | 0x5346_544e => Some(Filesystem::Ntfs),
+            // cifs, smb2 and the old smbfs.
+            0xff53_4d42 | 0xfe53_4d42 | 0x517b => Some(Filesystem::Cifs),
+            _ => None,
+        }
+    }
+
+    /// From the `f_fstypename` macOS and the BSDs report in statfs.
+    #[cfg(not(target_os = "linux"))]
+    pub fn from_type_name(name: &str) -> Option<Filesystem> {
+        match name {
+            "msdos" | "msdosfs" => Some(Filesystem::Fat),
+            "exfat" => Some(Filesystem::ExFat),
+            "ntfs" => Some(Filesystem::Ntfs),
+            "smbfs" => Some(Filesystem::Cifs),
+            _ => None,
+        }
+    }
+
+    /// The filesystem `path` is on, or would be once created; None for one that
+    /// keeps everything, or when it can't be told.
+    pub fn detect(path: &Path) -> Option<Filesystem> {
+        let existing = path.ancestors().find(|x| x.exists())?;
+        let stat = nix::sys::statfs::statfs(existing).ok()?;
+
+        #[cfg(target_os = "linux")]
+        return Filesystem::from_magic(stat.filesystem_type().0 as u32);
+        #[cfg(not(target_os = "linux"))]
+        return Filesystem::from_type_name(stat.filesystem_type_name());
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Filesystem::Fat => "FAT",
+            Filesystem::ExFat => "exFAT",
+            Filesystem::Ntfs => "NTFS",
+            Filesystem::Cifs => "an SMB share",
+        }
+    }
+
+    fn keeps_symlinks(&self) -> bool {
+        *self == Filesystem::Ntfs
+    }
+
+    fn keeps_links(options: &RsyncOptions) -> bool {
+        (options.archive || options.symlinks) && !options.copy_links
+    }
+
+    /// What `options` would have rsync try to keep that this filesystem can't.
+    pub fn unsupported(&self, options: &RsyncOptions) -> Vec<&'static str> {
+        let mut unsupported = Vec::new();
+        if options.archive || options.permissions {
+            unsupported.push("permissions");
+        }
+        if options.archive || options.group || options.super_mode {
+            unsupported.push("owners and groups");
+        }
+        if !self.keeps_symlinks() && Filesystem::keeps_links(options) {
+            unsupported.push("symbolic links");
+        }
+        unsupported
+    }
+
+    /// Turns off what `unsupported` lists, swapping -a for the -rt part of it
+    /// that still applies.
+    pub fn adjust(&self, options: &mut RsyncOptions) {
+        if options.archive {
+            options.archive = false;
+            options.recursive = true;
+            options.time = true;
+            options.symlinks = self.keeps_symlinks();
+        }
+        options.permissions = false;
+        options.group = false;
+        options.super_mode = false;
+        if !self.keeps_symlinks() && Filesystem::keeps_links(options) {
+            options.symlinks = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive() -> RsyncOptions {
+        RsyncOptions {
+            archive: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn maps_magic_numbers() {
+        assert!(Filesystem::from_magic(0x4d44) == Some(Filesystem::Fat));
+        assert!(Filesystem::from_magic(0x2011_bab0) == Some(Filesystem::ExFat));
+        assert!(Filesystem::from_magic(0x7366_746e) == Some(Filesystem::Ntfs));
+        assert!(Filesystem::from_magic(0x5346_544e) == Some(Filesystem::Ntfs));
+        assert!(Filesystem::from_magic(0xff53_4d42) == Some(Filesystem::Cifs));
+        assert!(Filesystem::from_magic(0xfe53_4d42) == Some(Filesystem::Cifs));
+        assert!(Filesystem::from_magic(0x517b) == Some(Filesystem::Cifs));
+        // ext4 and btrfs keep everything.
+        assert!(Filesystem::from_magic(0xef53).is_none());
+        assert!(Filesystem::from_magic(0x9123_683e).is_none());
+    }
+
+    #[test]
+    fn lists_and_drops_what_each_filesystem_cant_keep() {
+        for magic in [0x4d44, 0x2011_bab0, 0xff53_4d42] {
+            let filesystem = Filesystem::from_magic(magic).unwrap();
+            let mut options = archive();
+            assert_eq!(
+                filesystem.unsupported(&options),
+                ["permissions", "owners and groups", "symbolic links"]
+            );
+            filesystem.adjust(&mut options);
+            assert!(filesystem.unsupported(&options).is_empty());
+            assert!(options.recursive && options.time && !options.archive);
+        }
+
+        let ntfs = Filesystem::from_magic(0x7366_746e).unwrap();
+        let mut options = archive();
+        assert_eq!(
+            ntfs.unsupported(&options),
+            ["permissions", "owners and groups"]
+        );
+        ntfs.adjust(&mut options);
+        assert!(ntfs.unsupported(&options).is_empty());
+        assert!(options.symlinks);
+
+        // Links that are copied as files don't need keeping.
+        let options = RsyncOptions {
+            copy_links: true,
+            ..archive()
+        };
+        assert!(
+            !Filesystem::Fat
+                .unsupported(&options)
+                .contains(&"symbolic links")
+        );
+        assert!(
+            Filesystem::Fat
+                .unsupported(&RsyncOptions::default())
+                .is_empty()
+        );
+    }
+}
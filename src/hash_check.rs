@@ -0,0 +1,111 @@
+use eframe::egui;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Outcome for one sampled file: whether both sides hash the same, or why
+/// they couldn't be read.
+type Checked = (String, io::Result<bool>);
+
+/// SHA-256 comparison of a sample of the transferred files, running on its
+/// own thread after the transfer. Dropping it stops the check.
+pub struct HashCheck {
+    pub total: usize,
+    pub checked: usize,
+    pub mismatches: usize,
+    pub failures: usize,
+    cancel: Arc<AtomicBool>,
+    rx: Receiver<Checked>,
+}
+
+fn sha256_file(path: &Path) -> io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+impl HashCheck {
+    /// Hashes each of `paths` under both `src_base` and `dest_base`.
+    pub fn start(
+        src_base: PathBuf,
+        dest_base: PathBuf,
+        paths: Vec<String>,
+        ctx: &egui::Context,
+    ) -> HashCheck {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        let total = paths.len();
+        let thread_cancel = cancel.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            for path in paths {
+                if thread_cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                let matched = sha256_file(&src_base.join(&path))
+                    .and_then(|src| sha256_file(&dest_base.join(&path)).map(|dest| src == dest));
+                if tx.send((path, matched)).is_err() {
+                    return;
+                }
+                ctx.request_repaint();
+            }
+        });
+
+        HashCheck {
+            total,
+            checked: 0,
+            mismatches: 0,
+            failures: 0,
+            cancel,
+            rx,
+        }
+    }
+
+    /// Takes in the files checked since the last call and returns an error line for each one that failed.
+    pub fn poll(&mut self) -> Vec<String> {
+        let mut errors = Vec::new();
+        while let Ok((path, matched)) = self.rx.try_recv() {
+            self.checked += 1;
+            match matched {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.mismatches += 1;
+                    errors.push(format!("VERIFY FAIL: {} (src hash ≠ dest hash)", path));
+                }
+                Err(e) => {
+                    self.failures += 1;
+                    errors.push(format!("VERIFY FAIL: {} ({})", path, e));
+                }
+            }
+        }
+        errors
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.checked >= self.total
+    }
+
+    pub fn status(&self) -> String {
+        if !self.is_done() {
+            return format!("Checking hashes: {} of {}", self.checked, self.total);
+        }
+        match self.mismatches + self.failures {
+            0 => format!("Hash check passed — all {} sampled files match", self.total),
+            n => format!(
+                "Hash check failed — {} of {} sampled files differ or couldn't be read",
+                n, self.total
+            ),
+        }
+    }
+}
+
+impl Drop for HashCheck {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,198 @@
+/// Short descriptions of the rsync flags r-synced passes, keyed by the flag
+/// without its `=value` part.
+const FLAG_HELP: &[(&str, &str)] = &[
+    (
+        "-a",
+        "Archive mode: recurse and keep symlinks, permissions, times, group, owner and devices",
+    ),
+    ("-r", "Recurse into directories"),
+    ("-l", "Copy symlinks as symlinks"),
+    (
+        "-L",
+        "Copy the files symlinks point to instead of the links",
+    ),
+    (
+        "--copy-unsafe-links",
+        "Copy the files behind symlinks that point outside the tree",
+    ),
+    (
+        "-K",
+        "Treat symlinked directories on the destination as real directories",
+    ),
+    ("-p", "Keep permissions"),
+    ("-t", "Keep modification times"),
+    ("-g", "Keep the group"),
+    ("--atimes", "Keep access times"),
+    ("--crtimes", "Keep creation times"),
+    (
+        "--open-noatime",
+        "Don't update the access times of the files read",
+    ),
+    (
+        "--copy-as",
+        "Write the destination files as this user and group",
+    ),
+    ("-z", "Compress file data during the transfer"),
+    (
+        "--skip-compress",
+        "Don't compress files with these suffixes",
+    ),
+    ("-n", "Dry run: only show what would change"),
+    ("--dry-run", "Dry run: only show what would change"),
+    (
+        "-c",
+        "Compare files by checksum instead of size and modification time",
+    ),
+    ("-u", "Skip files that are newer at the destination"),
+    (
+        "--remove-source-files",
+        "Remove source files once they have been transferred",
+    ),
+    (
+        "--partial",
+        "Keep partially transferred files to resume from",
+    ),
+    (
+        "--partial-dir",
+        "Keep partially transferred files in this directory inside the destination",
+    ),
+    (
+        "--delete",
+        "Delete files in the destination that aren't in the source",
+    ),
+    ("--delete-before", "Delete before transferring"),
+    ("--delete-during", "Delete while transferring"),
+    ("--delete-after", "Delete after transferring"),
+    (
+        "--delete-delay",
+        "Find deletions while transferring, delete after",
+    ),
+    (
+        "--link-dest",
+        "Hard-link files that are unchanged from this directory",
+    ),
+    (
+        "--delay-updates",
+        "Put updated files into place at the end of the transfer",
+    ),
+    ("--bwlimit", "Limit the transfer speed, in KiB per second"),
+    ("--exclude", "Skip files matching the pattern"),
+    ("--include", "Don't skip files matching the pattern"),
+    ("--exclude-from", "Read exclude patterns from a file"),
+    ("--files-from", "Transfer only the files listed in a file"),
+    (
+        "-R",
+        "Use relative path names, recreating them under the destination",
+    ),
+    (
+        "--no-implied-dirs",
+        "Don't send the directories implied by a relative path",
+    ),
+    ("-e", "Remote shell to connect with"),
+    ("--rsync-path", "Program to run as rsync on the remote host"),
+    ("--ipv4", "Connect over IPv4 only"),
+    ("--ipv6", "Connect over IPv6 only"),
+    (
+        "--address",
+        "Local address to bind to for daemon connections",
+    ),
+    ("--out-format", "How each transferred file is reported"),
+    ("--stats", "Print transfer statistics at the end"),
+    ("--progress", "Show progress for each file"),
+    (
+        "--info",
+        "Extra output, e.g. progress2 for overall progress",
+    ),
+    ("--outbuf", "Output buffering; L flushes after every line"),
+    ("--log-file", "Also log what is done to this file"),
+    ("--log-file-format", "Format of the log file lines"),
+    ("-h", "Print numbers in a human-readable format"),
+    (
+        "--super",
+        "Receiver attempts super-user activities such as setting owners",
+    ),
+];
+
+pub fn flag_help(flag: &str) -> Option<&'static str> {
+    let name = flag.split('=').next().unwrap_or(flag);
+    FLAG_HELP
+        .iter()
+        .find(|(x, _)| *x == name)
+        .map(|(_, help)| *help)
+}
+
+// Excerpts from rsync(1), shown from the "?" next to each option.
+
+pub const ARCHIVE: &str = "This is equivalent to -rlptgoD. It is a quick way of saying you want recursion and want to \
+    preserve almost everything. Hard links, ACLs and extended attributes are not included.";
+pub const RECURSIVE: &str = "This tells rsync to copy directories recursively. Without it, directories given on the \
+    command line are skipped.";
+pub const LINKS: &str = "When symlinks are encountered, recreate the symlink on the destination. Without it, symlinks \
+    are skipped with a message.";
+pub const COPY_LINKS: &str = "When symlinks are encountered, the item that they point to (the referent) is copied, \
+    rather than the symlink.";
+pub const COPY_UNSAFE_LINKS: &str = "This tells rsync to copy the referent of symbolic links that point outside the \
+    copied tree. Absolute symlinks are also treated like ordinary files.";
+pub const KEEP_DIRLINKS: &str = "This option causes the receiving side to treat a symlink to a directory as though it \
+    were a real directory, but only if it matches a real directory from the sender. Without this option, the \
+    receiver's symlink would be deleted and replaced with a real directory.";
+pub const PERMS: &str = "This option causes the receiving rsync to set the destination permissions to be the same as \
+    the source permissions. Without it, new files get the source permissions masked by the receiving directory's \
+    default umask, and existing files keep theirs.";
+pub const TIMES: &str = "This tells rsync to transfer modification times along with the files and update them on the \
+    remote system. If this option is not used, the quick check that skips files that have not been modified cannot \
+    be effective.";
+pub const GROUP: &str = "This option causes rsync to set the group of the destination file to be the same as the \
+    source file. If the receiving program is not running as the super-user, only groups that the invoking user is \
+    a member of will be preserved.";
+pub const ATIMES: &str = "This tells rsync to set the access (use) times of the destination files to the same value as \
+    the source files. Both sides need rsync 3.2.0 or newer.";
+pub const CRTIMES: &str = "This tells rsync to set the create times (newness) of the destination files to the same \
+    value as the source files. Only systems that record creation times, such as macOS, support it.";
+pub const OPEN_NOATIME: &str = "This tells rsync to open files with the O_NOATIME flag (on systems that support it) \
+    to avoid changing the access time of the files that are being transferred.";
+pub const COPY_AS: &str = "This option instructs rsync to use the USER and (if specified after a colon) the GROUP for \
+    the copy operations. This only works if the user that is running rsync has the ability to change users. If the \
+    group is not specified then the user's default groups are used.";
+pub const SUPER: &str = "This tells the receiving side to attempt super-user activities even if the receiving rsync \
+    wasn't run by the super-user. These activities include preserving users, all groups and devices. When the \
+    receiving side lacks the privileges, these operations fail with errors.";
+pub const COMPRESS: &str = "With this option, rsync compresses the file data as it is sent to the destination machine, \
+    which reduces the amount of data being transmitted. This is useful over a slow connection, at the cost of CPU \
+    time on both sides.";
+pub const CHECKSUM: &str = "This changes the way rsync checks if the files have been changed and are in need of a \
+    transfer. Without it, rsync uses a quick check of each file's size and modification time; with it, files of the \
+    same size are compared by checksum, which means reading every file on both sides.";
+pub const UPDATE: &str = "This forces rsync to skip any files which exist on the destination and have a modified \
+    time that is newer than the source file. If an existing destination file has a modification time equal to the \
+    source file's, it will be updated if the sizes are different.";
+pub const LINK_DEST: &str = "Unchanged files are hard-linked from this directory into the destination instead of \
+    being copied, so each backup looks complete while only changed files take up space. A relative path is taken \
+    relative to the destination directory.";
+pub const DRY_RUN: &str = "This makes rsync perform a trial run that doesn't make any changes and produces mostly the \
+    same output as a real run. It is most commonly used to see what a command is going to do before running it.";
+pub const REMOVE_SOURCE_FILES: &str = "This tells rsync to remove from the sending side the files (meaning \
+    non-directories) that are a part of the transfer and have been successfully duplicated on the receiving side. \
+    Only use it on source files that nothing else is changing.";
+pub const PARTIAL: &str = "By default, rsync will delete any partially transferred file if the transfer is \
+    interrupted. With --partial the partial file is kept, which should make a subsequent transfer of the rest of \
+    the file much faster.";
+pub const DELETE: &str = "This tells rsync to delete extraneous files from the receiving side (ones that aren't on the \
+    sending side), but only for the directories that are being synchronized. This option can be dangerous if used \
+    incorrectly; try a dry run first.";
+pub const HUMAN_READABLE: &str = "Output numbers in a more human-readable format. Large numbers are grouped into \
+    thousands or shown with units such as K, M and G.";
+pub const PROGRESS2: &str = "--info=progress2 tells rsync to output statistics based on the whole transfer rather than \
+    individual files. The total can grow while rsync is still scanning directories.";
+pub const BWLIMIT: &str = "This option allows you to specify the maximum transfer rate for the data sent over the \
+    socket. Rsync writes data in blocks, so the limit is an average that short bursts can exceed.";
+pub const FILES_FROM: &str = "Using this option allows you to specify the exact list of files to transfer, read from a \
+    file. It implies --relative, so the paths in the list are kept, and -a no longer recurses into listed \
+    directories.";
+pub const RELATIVE: &str = "Use relative paths. The full path names given on the command line are sent to the server \
+    rather than just their last parts, so /foo/bar/baz.c arrives as foo/bar/baz.c under the destination.";
+pub const NO_IMPLIED_DIRS: &str = "This option affects the default behavior of --relative. Without it, the directories \
+    above each transferred path are recreated with the source's attributes; with it they are skipped, so existing \
+    directories or symlinks on the receiving side are left alone.";
+pub const OUTBUF: &str = "This sets the output buffering mode: None, Line or Block. Line buffering sends each line of \
+    output as soon as it is complete.";
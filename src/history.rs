@@ -0,0 +1,151 @@
+use crate::options::RsyncOptions;
+use crate::registry::normalize_endpoint;
+use crate::utils::split_remote;
+use anyhow::Context;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+const MAX_ENTRIES: usize = 50;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    #[serde(default)]
+    pub run_id: Option<String>,
+    pub started_at: DateTime<Local>,
+    pub duration_secs: f64,
+    pub command: String,
+    pub options: RsyncOptions,
+    pub stats: HashMap<String, String>,
+    pub files_transferred: u64,
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub verified: Option<bool>,
+    #[serde(default)]
+    pub raw_log: Option<PathBuf>,
+    #[serde(default)]
+    pub bytes_transferred: u64,
+    #[serde(default)]
+    pub avg_speed_bps: f64,
+    #[serde(default)]
+    pub max_speed_bps: f64,
+    #[serde(default)]
+    pub error_count: u64,
+    /// Stopped by the user rather than finished or failed.
+    #[serde(default)]
+    pub cancelled: bool,
+    pub logs: String,
+    pub error_logs: String,
+}
+
+impl HistoryEntry {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0) && self.verified != Some(false) && !self.cancelled
+    }
+}
+
+/// Aggregates over every recorded run to one destination.
+pub struct DestinationStats {
+    pub runs: usize,
+    pub total_bytes: u64,
+    pub avg_speed_bps: f64,
+    pub success_rate: f64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+}
+
+fn history_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|x| x.join("r-synced").join("history.json"))
+}
+
+impl History {
+    pub fn load() -> History {
+        history_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = history_path().context("Could not determine the data directory")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create the data directory")?;
+        }
+
+        let data = serde_json::to_string(self)?;
+        fs::write(&path, data).context("Failed to write history")?;
+
+        Ok(())
+    }
+
+    /// None if no run to `dest` has been recorded. Destinations are compared
+    /// the same way the active transfer registry does.
+    pub fn query_stats_for_destination(&self, dest: &str) -> Option<DestinationStats> {
+        let dest = normalize_endpoint(dest);
+        let entries = self
+            .entries
+            .iter()
+            .filter(|x| normalize_endpoint(&x.options.dest) == dest)
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return None;
+        }
+
+        let runs = entries.len();
+        Some(DestinationStats {
+            runs,
+            total_bytes: entries.iter().map(|x| x.bytes_transferred).sum(),
+            avg_speed_bps: entries.iter().map(|x| x.avg_speed_bps).sum::<f64>() / runs as f64,
+            success_rate: entries.iter().filter(|x| x.succeeded()).count() as f64 / runs as f64,
+        })
+    }
+
+    /// Bytes per second over every successful real transfer, or None before
+    /// the first one.
+    pub fn average_speed_bps(&self) -> Option<f64> {
+        let (bytes, secs) = self
+            .entries
+            .iter()
+            .filter(|x| x.succeeded() && !x.options.is_dry_run() && x.bytes_transferred > 0)
+            .fold((0u64, 0f64), |(bytes, secs), x| {
+                (bytes + x.bytes_transferred, secs + x.duration_secs)
+            });
+        (secs > 0.0).then(|| bytes as f64 / secs)
+    }
+
+    /// Where the newest successful transfer of `src` to another destination on
+    /// the same host as `dest` ended up, as a path on that host. Used as the
+    /// previous snapshot for --link-dest.
+    pub fn previous_snapshot(&self, src: &str, dest: &str) -> Option<String> {
+        let src = normalize_endpoint(src);
+        let host = split_remote(dest.trim()).map(|x| x.0);
+        self.entries
+            .iter()
+            .filter(|x| x.succeeded() && !x.options.is_dry_run())
+            .filter(|x| {
+                normalize_endpoint(&x.options.src) == src
+                    && normalize_endpoint(&x.options.dest) != normalize_endpoint(dest)
+            })
+            .find_map(|x| {
+                let previous = x.options.dest.trim();
+                match split_remote(previous) {
+                    Some((previous_host, path)) => {
+                        (host == Some(previous_host)).then(|| path.to_string())
+                    }
+                    None => host.is_none().then(|| previous.to_string()),
+                }
+            })
+    }
+
+    /// Newest entries are kept at the front; the oldest ones fall off once
+    /// the journal exceeds `MAX_ENTRIES`.
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.entries.insert(0, entry);
+        self.entries.truncate(MAX_ENTRIES);
+    }
+}
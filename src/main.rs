@@ -1,20 +1,85 @@
+mod cli;
+mod compare;
+mod config;
+mod defaults;
+mod diff_preview;
+mod draft;
+mod environment;
+mod error_log;
+mod file_log;
+mod filesystem;
+mod hash_check;
+mod help_text;
+mod history;
+mod monitors;
+mod options;
+mod presets;
+mod profiles;
+mod raw_log;
+mod registry;
+mod schedule;
+mod script;
+mod source_scan;
+mod stats_export;
 mod utils;
 
-use crate::utils::{format_bytes, parse_rsync_progress};
+use crate::cli::Cli;
+use crate::compare::Comparison;
+use crate::config::{Config, Theme, WindowGeometry};
+use crate::defaults::GlobalDefaults;
+use crate::diff_preview::DiffPreview;
+use crate::environment::{Check, CheckStatus};
+use crate::error_log::ErrorLog;
+use crate::file_log::FileLog;
+use crate::filesystem::Filesystem;
+use crate::hash_check::HashCheck;
+use crate::history::{History, HistoryEntry};
+use crate::monitors::{MonitorProbe, Monitors};
+use crate::options::{
+    DEFAULT_PARTIAL_DIR, DEFAULT_SKIP_COMPRESS, DEFAULT_VERIFY_SAMPLE, DeleteTiming, EnvOverride,
+    EnvVar, FilterKind, FilterRule, IoNiceClass, IpVersion, PASSTHROUGH_VARS, RsyncOptions,
+    is_secret,
+};
+use crate::presets::PRESETS;
+use crate::profiles::{LastRun, Profiles};
+use crate::raw_log::RawLog;
+use crate::registry::ActiveTransfer;
+use crate::schedule::{Schedule, ScheduledBatch};
+use crate::script::ScriptList;
+use crate::source_scan::{SourceEstimate, SourceScan};
+use crate::stats_export::StatsFormat;
+use crate::utils::{
+    ByteCount, DryRunSummary, ItemAction, ItemizedLine, RsyncVersion, Severity, common_base,
+    describe_exit_code, endpoint_name, estimate_eta, find_in_path, flag_supported, format_bytes,
+    format_count, format_duration, format_rough_duration, known_error_hint, new_run_id,
+    parse_human_number, parse_itemized_line, parse_rsync_number, parse_rsync_progress,
+    parse_speed_bps, random_sample, requires_text, shell_quote, split_remote, stderr_severity,
+};
 use anyhow::Context;
+use chrono::{DateTime, Local};
+use clap::Parser;
 use eframe::egui;
 use eframe::egui::{Checkbox, DragValue, ProgressBar, Vec2};
 use lazy_static::lazy_static;
 use nix::sys::signal;
 use nix::sys::signal::Signal;
-use nix::unistd::Pid;
+use nix::unistd::{AccessFlags, Pid, access};
 use regex::Regex;
-use std::collections::HashMap;
-use std::io::{BufRead, BufReader};
-use std::process::{Child, Command, Stdio};
-use std::sync::mpsc;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::IpAddr;
+use std::os::fd::OwnedFd;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex, mpsc};
 use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::NamedTempFile;
+use winit::event_loop::EventLoop;
 
 #[derive(Default)]
 struct Progress {
@@ -22,16 +87,18 @@ struct Progress {
     progress: f32,
 
     speed: String,
+    speed_bps: f64,
     time: String,
     bytes_sent: u64,
+    /// Files checked and total to check, from rsync's `to-chk` counter.
+    checked: Option<(u64, u64)>,
 }
 
-#[derive(Default)]
-struct Finished {}
-
 #[derive(Default)]
 struct NextFile {
     line: String,
+    /// Size from the itemized line, for files too small to get a progress line.
+    size: u64,
 }
 
 #[derive(Default)]
@@ -39,354 +106,4350 @@ struct Error {
     line: String,
 }
 
+#[derive(Default)]
+struct SourceRemoved {}
+
 enum StateMessage {
     Progress(Progress),
     NextFile(NextFile),
-    Finished(Finished),
+    Itemized(ItemizedLine),
+    SourceRemoved(SourceRemoved),
+    /// A `*deleting` line, with the overall progress it brings the run to.
+    Deleted(f32),
+    /// The exit code, or -1 if rsync was killed by a signal.
+    FinishedWithStatus(i32),
     Error(Error),
+    /// Every line rsync prints, before any parsing.
+    Raw(String),
+    /// The --stats block printed at the end of the transfer.
+    Stats(String),
 }
 
-#[derive(Default)]
-struct AppState {
+/// How long the source path has to stay unchanged before it is walked for an estimate.
+const SCAN_DEBOUNCE: Duration = Duration::from_secs(1);
+
+const ISSUES_URL: &str = "https://github.com/vitiacat/r-synced/issues/new";
+
+/// Where a plain log line came from, which decides its color.
+#[derive(Clone, Copy, PartialEq)]
+enum LogKind {
+    Info,
+    Created,
+    Updated,
+    Deleted,
+    Directory,
+    Warning,
+    Error,
+}
+
+impl LogKind {
+    fn from_action(action: ItemAction) -> LogKind {
+        match action {
+            ItemAction::New => LogKind::Created,
+            ItemAction::Deleted => LogKind::Deleted,
+            ItemAction::Directory => LogKind::Directory,
+            ItemAction::Updated | ItemAction::Other => LogKind::Updated,
+        }
+    }
+
+    fn from_severity(severity: Severity) -> LogKind {
+        match severity {
+            Severity::Warning => LogKind::Warning,
+            Severity::Error => LogKind::Error,
+        }
+    }
+
+    /// None keeps the theme's text color.
+    fn color(&self, visuals: &egui::Visuals) -> Option<egui::Color32> {
+        match self {
+            LogKind::Info | LogKind::Updated => None,
+            LogKind::Created if visuals.dark_mode => Some(egui::Color32::LIGHT_GREEN),
+            LogKind::Created => Some(egui::Color32::DARK_GREEN),
+            LogKind::Deleted | LogKind::Error => Some(visuals.error_fg_color),
+            LogKind::Directory => Some(visuals.weak_text_color()),
+            LogKind::Warning => Some(visuals.warn_fg_color),
+        }
+    }
+}
+
+struct LogLine {
+    kind: LogKind,
+    text: String,
+}
+
+impl LogLine {
+    fn new(kind: LogKind, text: impl Into<String>) -> LogLine {
+        LogLine {
+            kind,
+            text: text.into(),
+        }
+    }
+}
+
+#[derive(Default, PartialEq)]
+enum Phase {
+    #[default]
+    Transfer,
+    Verify,
+}
+
+/// A spot-check verification in progress: how many files were sampled and
+/// the --files-from list holding them.
+struct SpotCheck {
+    files: usize,
+    _list: NamedTempFile,
+}
+
+/// A transfer whose dry-run is done, waiting for the deletions to be confirmed.
+struct PlannedRun {
+    options: RsyncOptions,
+    stats: HashMap<String, String>,
+    files_count: u64,
+    deletions: Vec<String>,
+    /// Rough count of the files the destination holds now: the source files
+    /// that aren't new, plus the files that would be deleted.
+    dest_files: u64,
+    /// Ticked by the user when most of the destination would be deleted.
+    mass_deletion_confirmed: bool,
+    lists: ListFiles,
+    active: ActiveTransfer,
+    /// The profile this run was started from, and whether the scheduler did it.
+    profile: Option<(String, bool)>,
+}
+
+struct RunInfo {
+    id: String,
+    started: Instant,
+    started_at: DateTime<Local>,
+    command: String,
+    options: RsyncOptions,
+    stats: HashMap<String, String>,
+    files_count: u64,
+    files_transferred: u64,
+    /// Sum and count of the reported rates, for the average speed at the end.
+    speed_sum: f64,
+    speed_samples: u64,
+    max_speed_bps: f64,
+    error_count: u64,
+    bytes: ByteCount,
+    exit_code: Option<i32>,
+    verified: Option<bool>,
+    /// Kept alive until the run is recorded, which deletes the files.
+    lists: ListFiles,
+    raw_log: Option<Arc<Mutex<RawLog>>>,
+    profile: Option<(String, bool)>,
+    _active: ActiveTransfer,
+}
+
+/// The compare window: the endpoints compared and, once done, the outcome.
+struct CompareView {
     src: String,
     dest: String,
+    result: Option<Result<Comparison, String>>,
+}
+
+#[derive(Default)]
+struct AppState {
+    options: RsyncOptions,
     progress: Option<Receiver<StateMessage>>,
-    logs: String,
-    error_logs: String,
+    logs: Vec<LogLine>,
+    error_logs: ErrorLog,
     current_progress: Progress,
     is_finished: bool,
-    child: Option<Child>,
+    child_pid: Option<u32>,
+    /// Exit code of the last transfer, kept for display after it is recorded.
+    exit_code: Option<i32>,
+    /// "<profile or source> → <host or destination> [#run id]", kept after the run is recorded.
+    progress_title: String,
+    confirm_cancel: bool,
+    /// Started without a dry-run and rsync hasn't reported its file count yet.
+    scan_pending: bool,
+    /// Last title sent to the viewport, so it is only changed when needed.
+    window_title: String,
+    /// The user stopped the current or last run.
+    cancelled: bool,
+    /// rsync was sent SIGSTOP and waits for SIGCONT.
+    paused: bool,
+    show_settings: bool,
+    planned: Option<PlannedRun>,
+    profiles: Profiles,
+    show_profiles: bool,
+    /// The profile loaded into the form, if any.
+    selected_profile: Option<String>,
+    /// The form as it was before a profile was selected.
+    unsaved_options: Option<RsyncOptions>,
+    /// Name being edited inline: (current name, new name).
+    renaming_profile: Option<(String, String)>,
+    /// Waiting for the deletion to be confirmed.
+    delete_profile: Option<String>,
+    profile_name: String,
+    /// Picked up by the next `start_run`.
+    run_profile: Option<(String, bool)>,
+    /// Scheduled runs since the scheduler last found nothing due.
+    scheduled_batch: Option<ScheduledBatch>,
+    /// Summary of the last batch once it drained, until dismissed.
+    batch_summary: Option<String>,
+    diff_preview: Option<DiffPreview>,
+    compare: Option<CompareView>,
+    source_estimate: Option<SourceEstimate>,
+    source_scan: Option<SourceScan>,
+    /// The source path the estimate is for, to notice edits.
+    estimate_src: String,
+    source_edited_at: Option<Instant>,
+    compare_rx: Option<Receiver<Result<Comparison, String>>>,
+    /// Counts from the dry-run of the current or last run.
+    dry_run_summary: Option<DryRunSummary>,
+    /// Bytes the last dry-run would send, shown until the transfer starts.
+    transfer_estimate: Option<u64>,
+    /// Shown in the progress window until it is dismissed.
+    summary: Option<String>,
+    /// Parsed --stats output of the transfer itself, unlike the dry-run stats in `RunInfo`.
+    final_stats: HashMap<String, String>,
+    run: Option<RunInfo>,
+    phase: Phase,
+    verify_diffs: Vec<String>,
+    verify_passed: bool,
+    spot_check: Option<SpotCheck>,
+    hash_check: Option<HashCheck>,
+    file_log: FileLog,
+    show_plain_logs: bool,
+    raw_output: VecDeque<String>,
+    /// Status lines that are also written to the plain logs, shown under the file table.
+    notices: Vec<String>,
+    removed_count: u64,
+    confirm_move: bool,
+    /// The destination the filesystem was last detected for, and what was found.
+    dest_filesystem: (String, Option<Filesystem>),
+    /// Index into `PRESETS` of the preset waiting for its confirmation.
+    confirm_preset: Option<usize>,
+    form_notice: Option<String>,
+    overall_progress: bool,
 
-    archive: bool,
-    recursive: bool,
-    symlinks: bool,
-    permissions: bool,
-    time: bool,
-    group: bool,
-    compress: bool,
-    dry_run: bool,
-    checksum: bool,
+    history: History,
+    show_history: bool,
+    selected_history: Option<usize>,
+    config: Config,
 
-    limit_bw: bool,
-    bwlimit_kbps: u32,
+    local_addresses: Vec<IpAddr>,
+    /// Connected monitors as seen at startup, primary first.
+    monitors: Vec<egui::Rect>,
+    window_checked: bool,
+    focus_source: bool,
+    /// SSH connection retries done for the current run, and when the next one starts.
+    ssh_attempt: u32,
+    retry_at: Option<Instant>,
 
-    excluded: String,
-    included: String,
+    rsync_version: Option<RsyncVersion>,
+    /// The binary `rsync_version` was detected for; None until the first detection.
+    rsync_version_for: Option<String>,
+    rsync_version_rx: Option<Receiver<Option<RsyncVersion>>>,
+    editing_rsync_binary: bool,
+
+    /// Filter rule being dragged by its handle, and where it would be dropped.
+    drag_index: Option<usize>,
+    hover_index: Option<usize>,
+
+    /// Serialized form state, compared every frame to notice edits.
+    draft_snapshot: String,
+    draft_changed_at: Option<Instant>,
+    /// A draft left behind by a previous session, offered for restoring.
+    restore_draft: Option<RsyncOptions>,
+    /// Path typed into the export window while it is open.
+    export_script: Option<String>,
+    /// Path and format typed into the stats export window while it is open.
+    export_stats: Option<(String, StatsFormat)>,
+    connection_test: Option<Receiver<Result<(), String>>>,
+    env_checks: Vec<Check>,
+    env_check_rx: Option<Receiver<Vec<Check>>>,
+    env_checked: bool,
+    connection_status: Option<Result<(), String>>,
 }
 
-fn create_rsync_command(state: &AppState) -> Command {
-    let mut cmd = Command::new("rsync");
+/// How long the form has to stay unchanged before the draft is written.
+const DRAFT_DEBOUNCE: Duration = Duration::from_secs(2);
 
-    cmd.arg("-i");
-    cmd.arg("--progress");
+lazy_static! {
+    /// Looked up once, since the priority settings ask for it every frame.
+    static ref IONICE_AVAILABLE: bool = cfg!(target_os = "linux") && find_in_path("ionice").is_some();
+}
 
-    if state.archive {
-        cmd.arg("-a");
-    } else {
-        if state.recursive {
-            cmd.arg("-r");
-        }
-        if state.symlinks {
-            cmd.arg("-l");
+/// The nice level is applied inside the forked child right before exec, and the
+/// ionice wrapper execs rsync in place, so the spawned PID is always rsync's own
+/// and signals sent to the child still reach it.
+fn rsync_program(options: &RsyncOptions) -> Command {
+    if !options.low_priority {
+        return Command::new(options.rsync_binary());
+    }
+
+    let mut cmd = match options.ionice_class {
+        Some(class) if *IONICE_AVAILABLE => {
+            let mut cmd = Command::new("ionice");
+            cmd.arg("-c").arg(class.as_arg());
+            cmd.arg(options.rsync_binary());
+            cmd
         }
-        if state.permissions {
-            cmd.arg("-p");
+        _ => Command::new(options.rsync_binary()),
+    };
+
+    let nice_level = options.nice_level;
+    // SAFETY: setpriority is async-signal-safe and touches no state shared with the parent.
+    unsafe {
+        cmd.pre_exec(move || {
+            // Raising the priority needs privileges; keep going at the default one if denied.
+            nix::libc::setpriority(nix::libc::PRIO_PROCESS, 0, nice_level.into());
+            Ok(())
+        });
+    }
+
+    cmd
+}
+
+fn apply_env(cmd: &mut Command, options: &RsyncOptions) {
+    for (key, env) in &options.env_overrides {
+        match env {
+            EnvOverride::Set(value) => cmd.env(key, value),
+            EnvOverride::Unset => cmd.env_remove(key),
+        };
+    }
+    for var in &options.env_vars {
+        if !var.key.trim().is_empty() {
+            cmd.env(var.key.trim(), &var.value);
         }
-        if state.time {
-            cmd.arg("-t");
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CommandPartKind {
+    Env,
+    Program,
+    Flag,
+    Value,
+}
+
+/// Shell-quoted words of the command, with secret environment values masked.
+fn command_parts(cmd: &Command, options: &RsyncOptions) -> Vec<(CommandPartKind, String)> {
+    let mut parts = Vec::new();
+
+    let unset: Vec<_> = options
+        .env_overrides
+        .iter()
+        .filter(|(_, env)| **env == EnvOverride::Unset)
+        .map(|(key, _)| format!("-u {}", key))
+        .collect();
+    if !unset.is_empty() {
+        parts.push((CommandPartKind::Env, format!("env {}", unset.join(" "))));
+    }
+    for (key, env) in &options.env_overrides {
+        if let EnvOverride::Set(value) = env {
+            let value = if is_secret(key, value) { "***" } else { value };
+            parts.push((
+                CommandPartKind::Env,
+                format!("{}={}", key, shell_quote(value)),
+            ));
         }
-        if state.group {
-            cmd.arg("-g");
+    }
+    for var in &options.env_vars {
+        if var.key.trim().is_empty() {
+            continue;
         }
+        let value = if var.is_secret() { "***" } else { &var.value };
+        parts.push((
+            CommandPartKind::Env,
+            format!("{}={}", var.key.trim(), shell_quote(value)),
+        ));
     }
 
-    if state.compress {
-        cmd.arg("-z");
+    parts.push((
+        CommandPartKind::Program,
+        shell_quote(&cmd.get_program().to_string_lossy()),
+    ));
+    for arg in cmd.get_args() {
+        let arg = arg.to_string_lossy();
+        let kind = if arg.starts_with('-') {
+            CommandPartKind::Flag
+        } else {
+            CommandPartKind::Value
+        };
+        parts.push((kind, shell_quote(&arg)));
     }
 
-    if state.dry_run {
-        cmd.arg("-n");
-    }
+    parts
+}
 
-    if state.checksum {
-        cmd.arg("-c");
+/// The command as it could be pasted into a shell, with secrets masked.
+fn format_command(cmd: &Command, options: &RsyncOptions) -> String {
+    command_parts(cmd, options)
+        .into_iter()
+        .map(|(_, x)| x)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Two options that don't work in this combination. `a` is the one that is
+/// set, `b` the one it clashes with or depends on.
+struct ConflictWarning {
+    a: &'static str,
+    b: &'static str,
+    message: String,
+    /// rsync would reject or misbehave with it, so Run waits until it is
+    /// resolved. Otherwise the option just has no effect.
+    blocking: bool,
+}
+
+impl ConflictWarning {
+    fn color(&self, visuals: &egui::Visuals) -> egui::Color32 {
+        if self.blocking {
+            egui::Color32::RED
+        } else {
+            visuals.warn_fg_color
+        }
     }
+}
+
+fn blocks_run(conflicts: &[ConflictWarning]) -> bool {
+    conflicts.iter().any(|x| x.blocking)
+}
 
-    if state.limit_bw {
-        cmd.arg(format!("--bwlimit={}", state.bwlimit_kbps));
+/// Flags rsync would reject or ignore in this combination, with the reason.
+fn check_conflicts(options: &RsyncOptions) -> Vec<ConflictWarning> {
+    let mut conflicts = Vec::new();
+
+    if options.delete && !options.archive && !options.recursive {
+        conflicts.push(ConflictWarning {
+            a: "--delete",
+            b: "-r",
+            message: "--delete only works together with Recursive (-r) or Archive (-a)".to_string(),
+            blocking: true,
+        });
+    }
+    // --files-from turns on --relative by itself.
+    if options.no_implied_dirs && !options.relative && !options.files_from {
+        conflicts.push(ConflictWarning {
+            a: "--no-implied-dirs",
+            b: "-R",
+            message: "--no-implied-dirs has no effect without Relative paths (-R)".to_string(),
+            blocking: false,
+        });
     }
+    // rsync refuses to put the delayed updates into a directory outside the destination.
+    if options.delete
+        && options.delete_timing == DeleteTiming::Delay
+        && let Some(dir) = options.partial_dir().filter(|x| Path::new(x).is_absolute())
+    {
+        conflicts.push(ConflictWarning {
+            a: "--delete-delay",
+            b: "--partial-dir",
+            message: format!(
+                "--delete-delay can't be combined with an absolute --partial-dir ({})",
+                dir
+            ),
+            blocking: true,
+        });
+    }
+
+    conflicts
+}
 
-    for excluded in state.excluded.lines() {
-        cmd.arg("--exclude").arg(excluded);
+/// Frames `response` if `flag` is part of one of the conflicts, in red if
+/// that one blocks the run.
+fn outline_conflict(
+    ui: &egui::Ui,
+    response: &egui::Response,
+    flag: &str,
+    conflicts: &[ConflictWarning],
+) {
+    let mut matching = conflicts.iter().filter(|x| x.a == flag || x.b == flag);
+    if let Some(conflict) = matching
+        .clone()
+        .find(|x| x.blocking)
+        .or_else(|| matching.next())
+    {
+        ui.painter().rect_stroke(
+            response.rect.expand(2.0),
+            2.0,
+            egui::Stroke::new(1.5, conflict.color(ui.visuals())),
+            egui::StrokeKind::Outside,
+        );
     }
+}
+
+/// The transfer command for the current options, with placeholders for the
+/// list files since those only exist once a run starts.
+fn preview_command(options: &RsyncOptions, version: Option<&RsyncVersion>) -> Command {
+    let lists = ListPaths {
+        exclude_from: (!options.exclude_from_content.trim().is_empty())
+            .then(|| Path::new("<exclude file>")),
+        files_from: options
+            .files_from
+            .then(|| match options.files_from_file.trim() {
+                "" => Path::new("<file list>"),
+                x => Path::new(x),
+            }),
+    };
+
+    create_rsync_command(options, lists, version)
+}
+
+fn command_preview(ui: &mut egui::Ui, options: &RsyncOptions, version: Option<&RsyncVersion>) {
+    let command = preview_command(options, version);
+    let conflicts = check_conflicts(options);
 
-    for included in state.included.lines() {
-        cmd.arg("--include").arg(included);
+    let mut job = egui::text::LayoutJob::default();
+    for (kind, part) in command_parts(&command, options) {
+        let conflict = conflicts
+            .iter()
+            .filter(|_| kind == CommandPartKind::Flag)
+            .find(|x| part.split('=').next() == Some(x.a));
+        let color = match kind {
+            _ if let Some(conflict) = conflict => conflict.color(ui.visuals()),
+            CommandPartKind::Env => egui::Color32::GRAY,
+            CommandPartKind::Program => egui::Color32::from_rgb(90, 150, 230),
+            CommandPartKind::Flag => egui::Color32::from_rgb(210, 170, 60),
+            CommandPartKind::Value => egui::Color32::from_rgb(80, 180, 80),
+        };
+
+        if !job.is_empty() {
+            job.append(" ", 0.0, egui::TextFormat::default());
+        }
+        job.append(
+            &part,
+            0.0,
+            egui::TextFormat::simple(egui::TextStyle::Monospace.resolve(ui.style()), color),
+        );
     }
 
-    cmd.arg(&state.src);
-    cmd.arg(&state.dest);
+    egui::ScrollArea::horizontal()
+        .id_salt("command_preview")
+        .show(ui, |ui| {
+            ui.add(egui::Label::new(job).extend());
+        });
+}
 
-    cmd
+/// Panics in debug builds if `cmd` uses a flag the detected rsync doesn't have;
+/// the builders are expected to fall back or leave such flags out.
+fn debug_assert_supported(cmd: &Command, version: Option<&RsyncVersion>) {
+    for arg in cmd.get_args() {
+        let arg = arg.to_string_lossy();
+        debug_assert!(
+            flag_supported(version, &arg),
+            "{} is not supported by the detected rsync",
+            arg
+        );
+    }
 }
 
-fn create_rsync_dry_run_command(state: &AppState) -> Command {
-    let mut cmd = Command::new("rsync");
+fn create_rsync_command(
+    options: &RsyncOptions,
+    lists: ListPaths,
+    version: Option<&RsyncVersion>,
+) -> Command {
+    let mut cmd = rsync_program(options);
 
-    cmd.arg("-e")
-        .arg("ssh -o PasswordAuthentication=no -o PreferredAuthentications=publickey");
-    cmd.arg("-an");
+    // Same as -i, plus the file size for the file log.
+    cmd.arg("--out-format=%i %l %n%L");
     cmd.arg("--stats");
-
-    for excluded in state.excluded.lines() {
-        cmd.arg("--exclude").arg(excluded);
+    if options.overall_progress && flag_supported(version, "--info=progress2") {
+        cmd.arg("--info=progress2");
+    } else {
+        cmd.arg("--progress");
+    }
+    if options.line_buffered && flag_supported(version, "--outbuf=L") {
+        cmd.arg("--outbuf=L");
     }
 
-    for included in state.included.lines() {
-        cmd.arg("--include").arg(included);
+    if let Some(log_file) = options.log_file() {
+        cmd.arg(format!("--log-file={}", log_file));
+        if !options.log_file_format.trim().is_empty() {
+            cmd.arg(format!(
+                "--log-file-format={}",
+                options.log_file_format.trim()
+            ));
+        }
     }
 
-    cmd.arg(&state.src);
-    cmd.arg(&state.dest);
+    push_transfer_args(&mut cmd, options, lists, version);
+    debug_assert_supported(&cmd, version);
 
     cmd
 }
 
-fn run_rsync(
-    mut cmd: Command,
-    files_count: u64,
-    ctx: egui::Context,
-) -> (Receiver<StateMessage>, Child) {
-    let (tx, rx) = mpsc::channel::<StateMessage>();
+fn create_rsync_verify_command(
+    options: &RsyncOptions,
+    lists: ListPaths,
+    version: Option<&RsyncVersion>,
+) -> Command {
+    let mut cmd = rsync_program(options);
 
-    let mut child = cmd
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .expect("");
-    let stdout = child.stdout.take().unwrap();
-    let stderr = child.stderr.take().unwrap();
-    let mut reader = BufReader::new(stdout);
-    let err_reader = BufReader::new(stderr);
-    let mut buffer = Vec::new();
+    // Itemizing twice also lists unchanged files, so every checked file advances the progress.
+    cmd.arg("-ii");
+    push_transfer_args(
+        &mut cmd,
+        &RsyncOptions {
+            dry_run: true,
+            checksum: true,
+            ..options.clone()
+        },
+        lists,
+        version,
+    );
+    debug_assert_supported(&cmd, version);
 
-    let cloned_tx = tx.clone();
+    cmd
+}
 
-    thread::spawn(move || {
-        for line in err_reader.lines() {
-            if let Ok(line) = line {
-                cloned_tx.send(StateMessage::Error(Error { line })).unwrap();
-            }
-        }
-    });
+/// Checksums only the files in `list` against the destination.
+fn create_rsync_spot_check_command(
+    options: &RsyncOptions,
+    list: &Path,
+    version: Option<&RsyncVersion>,
+) -> Command {
+    let options = RsyncOptions {
+        src: spot_check_source(&options.src),
+        delete: false,
+        ..options.clone()
+    };
+    let lists = ListPaths {
+        exclude_from: None,
+        files_from: Some(list),
+    };
 
-    thread::spawn(move || {
-        let mut count = 0;
-        let mut data = (String::from("N/A"), String::from("N/A"), 0, 0);
+    create_rsync_verify_command(&options, lists, version)
+}
 
-        while let Ok(bytes_read) = reader.read_until(b'\r', &mut buffer) {
-            if bytes_read == 0 {
-                break;
-            }
+/// The directory the itemized paths are relative to: the source itself when
+/// it ends with a slash, otherwise its parent.
+fn spot_check_source(src: &str) -> String {
+    let src = src.trim();
+    if src.ends_with('/') {
+        return src.to_string();
+    }
 
-            if let Ok(line_str) = str::from_utf8(&buffer) {
-                let trimmed_line = line_str.trim_end_matches(['\r', '\n']).trim();
-                let lines = trimmed_line.lines();
+    let path = split_remote(src).map_or(src, |(_, path)| path);
+    let prefix = &src[..src.len() - path.len()];
+    match path.rfind('/') {
+        Some(i) => format!("{}{}", prefix, &path[..=i]),
+        None if prefix.is_empty() => "./".to_string(),
+        None => prefix.to_string(),
+    }
+}
 
-                for line in lines {
-                    let p = parse_rsync_progress(line);
-                    if let Some(progress) = p {
-                        data = (
-                            progress.speed,
-                            progress.estimated_time,
-                            progress.bytes_transferred,
-                            progress.percentage,
-                        );
+/// Whether rsync will be able to write `path`, without creating it: an
+/// existing file has to be writable, a new one needs a writable directory.
+fn check_writable(path: &Path) -> nix::Result<()> {
+    let target = match path.parent() {
+        _ if path.exists() => path,
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    access(target, AccessFlags::W_OK)
+}
 
-                        tx.send(StateMessage::Progress(Progress {
-                            progress: data.3 as f32 / 100.0,
-                            total_progress: count as f32 / files_count as f32,
-                            speed: data.0.clone(),
-                            time: data.1.clone(),
-                            bytes_sent: data.2,
-                        }))
-                        .unwrap();
+fn ssh_command(options: &RsyncOptions) -> String {
+    let mut ssh = String::from("ssh");
 
-                        ctx.request_repaint();
-                    }
+    match options.force_ipv {
+        Some(IpVersion::V4) => ssh.push_str(" -4"),
+        Some(IpVersion::V6) => ssh.push_str(" -6"),
+        None => {}
+    }
 
-                    if line.starts_with(|x| x == '>') || line.starts_with(|x| x == '<') {
-                        count += 1;
+    if options.use_ssh_multiplexing
+        && let Some(path) = ssh_control_path()
+    {
+        ssh.push_str(&format!(
+            " -o ControlMaster=auto -o ControlPath={} -o ControlPersist=60",
+            shell_quote(&path)
+        ));
+    }
 
-                        tx.send(StateMessage::NextFile(NextFile {
-                            line: line
-                                .to_string()
-                                .split(" ")
-                                .last()
-                                .unwrap_or_default()
-                                .to_string(),
-                        }))
-                        .unwrap();
+    ssh
+}
 
-                        ctx.request_repaint();
-                    }
-                    println!("[rsync]: {}", line);
-                }
-            }
+/// Socket of the shared SSH connection, in a directory only this user can
+/// write to so nobody else can slip in a master of their own. ssh fills in
+/// %C with a hash of the connection, which keeps the name short and free of
+/// characters from the host name. None when there is no such directory, and
+/// the connection isn't shared then.
+fn ssh_control_path() -> Option<String> {
+    let dir = dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .or_else(|| dirs::home_dir().map(|x| x.join(".ssh")))?;
+    Some(dir.join("r-synced-%C").to_string_lossy().into_owned())
+}
 
-            buffer.clear();
-        }
+/// Asks the shared SSH connections to the remote endpoints of `options` to
+/// close, without waiting for them.
+fn close_ssh_masters(options: &RsyncOptions) {
+    let Some(control_path) = ssh_control_path() else {
+        return;
+    };
 
-        tx.send(StateMessage::Finished(Default::default())).unwrap();
-        ctx.request_repaint();
-    });
+    for endpoint in [&options.src, &options.dest] {
+        let endpoint = endpoint.trim();
+        if split_remote(endpoint).is_none() || is_daemon_endpoint(endpoint) {
+            continue;
+        }
+        // The login part, user included, as it was given to ssh.
+        let Some((login, _)) = endpoint.split_once(':') else {
+            continue;
+        };
 
-    (rx, child)
+        let mut cmd = Command::new("ssh");
+        cmd.arg("-o")
+            .arg(format!("ControlPath={}", control_path))
+            .args(["-O", "exit", login])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        thread::spawn(move || {
+            let _ = cmd.status();
+        });
+    }
 }
 
-fn parse_rsync_stats(lines: &String) -> HashMap<String, String> {
-    let mut stats: HashMap<String, String> = HashMap::new();
+/// The remote shell given under Advanced, falling back to $RSYNC_RSH like rsync does.
+fn custom_rsh(options: &RsyncOptions) -> Option<String> {
+    Some(options.custom_rsh.trim())
+        .filter(|x| !x.is_empty())
+        .map(str::to_string)
+        .or_else(|| options.env_value("RSYNC_RSH"))
+        .filter(|x| !x.trim().is_empty())
+}
 
-    lazy_static! {
-        static ref RE_KEY_VALUE: Regex = Regex::new(r"^(.+?):\s*(.*)$").unwrap();
-        static ref RE_NUM_FILES: Regex = Regex::new(
-            r"([\d.]+)\s+\(reg:\s*([\d.]+),\s*dir:\s*([\d.]+)(?:,\s*link:\s*([\d.]+))?\s*\)"
-        )
-        .unwrap();
-        static ref RE_TOTAL_SPEEDUP: Regex =
-            Regex::new(r"total size is ([\d.]+)\s+speedup is ([\d.,]+)\s+\((.*)\)").unwrap();
+/// What rsync will connect to a remote shell endpoint with, and where that
+/// comes from. None when neither endpoint goes through a remote shell.
+fn transport_text(options: &RsyncOptions) -> Option<String> {
+    let remote_shell = |x: &str| split_remote(x.trim()).is_some() && !is_daemon_endpoint(x.trim());
+    if !remote_shell(&options.src) && !remote_shell(&options.dest) {
+        return None;
     }
 
-    for line in lines.lines() {
-        let trimmed_line = line.trim();
-        if trimmed_line.is_empty() {
-            continue;
+    Some(match custom_rsh(options) {
+        Some(rsh) if options.custom_rsh.trim().is_empty() => {
+            format!("Transport: {} (from RSYNC_RSH)", rsh.trim())
         }
+        Some(rsh) => format!("Transport: {} (custom)", rsh.trim()),
+        None => format!("Transport: {} (default)", ssh_command(options)),
+    })
+}
 
-        if let Some(caps) = RE_KEY_VALUE.captures(trimmed_line) {
-            let key = caps.get(1).unwrap().as_str().trim().to_string();
-            let value = caps.get(2).unwrap().as_str().trim().to_string();
-
-            if key == "Number of files" {
-                if let Some(num_caps) = RE_NUM_FILES.captures(&value) {
-                    stats.insert(
-                        "Number of files (total)".to_string(),
-                        num_caps
-                            .get(1)
-                            .map(|x| x.as_str())
-                            .unwrap_or_default()
-                            .to_string(),
-                    );
-                    stats.insert(
-                        "Number of files (regular)".to_string(),
-                        num_caps
-                            .get(2)
-                            .map(|x| x.as_str())
-                            .unwrap_or_default()
-                            .to_string(),
-                    );
-                    stats.insert(
-                        "Number of files (directories)".to_string(),
-                        num_caps
-                            .get(3)
-                            .map(|x| x.as_str())
-                            .unwrap_or_default()
-                            .to_string(),
-                    );
-                    stats.insert(
-                        "Number of files (links)".to_string(),
-                        num_caps
-                            .get(4)
-                            .map(|x| x.as_str())
-                            .unwrap_or_default()
-                            .to_string(),
-                    );
-                }
-            } else {
-                stats.insert(key, value);
-            }
-        } else if let Some(caps) = RE_TOTAL_SPEEDUP.captures(trimmed_line) {
-            stats.insert(
-                "Total size (summary)".to_string(),
-                caps.get(1).unwrap().as_str().to_string(),
-            );
-            stats.insert(
-                "Speedup".to_string(),
-                caps.get(2).unwrap().as_str().to_string(),
-            );
-            stats.insert(
-                "Run type".to_string(),
-                caps.get(3).unwrap().as_str().to_string(),
-            );
+fn push_ip_version_args(cmd: &mut Command, options: &RsyncOptions) {
+    match options.force_ipv {
+        Some(IpVersion::V4) => {
+            cmd.arg("--ipv4");
+        }
+        Some(IpVersion::V6) => {
+            cmd.arg("--ipv6");
         }
+        None => {}
     }
+}
 
-    stats
+fn push_rsync_path_arg(cmd: &mut Command, options: &RsyncOptions) {
+    let path = options.remote_rsync_path.trim();
+    if !path.is_empty() {
+        cmd.arg(format!("--rsync-path={}", path));
+    }
 }
 
-impl eframe::App for AppState {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if let Some(rx) = &self.progress {
-            while let Ok(msg) = rx.try_recv() {
-                match msg {
-                    StateMessage::Progress(x) => self.current_progress = x,
-                    StateMessage::NextFile(x) => {
-                        if !x.line.is_empty() {
-                            self.logs.push_str(&x.line);
-                            self.logs.push('\n');
-                        }
-                    }
-                    StateMessage::Finished(_) => {
-                        self.is_finished = true;
-                        self.child = None;
-                    }
-                    StateMessage::Error(x) => {
-                        self.error_logs.push_str(&x.line);
-                        self.error_logs.push('\n');
-                    }
-                }
-            }
-        }
+fn bind_address(options: &RsyncOptions) -> Option<IpAddr> {
+    options.bind_address.trim().parse::<IpAddr>().ok()
+}
 
-        ctx.set_pixels_per_point(1.2);
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("r-synced");
-            if self.progress.is_some() {
-                egui::Window::new("Operation Progress")
-                    .collapsible(false)
-                    .resizable(false)
-                    .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
-                    .show(ctx, |ui| {
-                        ui.group(|ui| {
-                            let progress_bar = ProgressBar::new(self.current_progress.total_progress)
-                                .show_percentage()
-                                .text(format!("{:.0}%", self.current_progress.total_progress * 100.0));
-                            ui.add(progress_bar);
+fn is_daemon_endpoint(endpoint: &str) -> bool {
+    endpoint.starts_with("rsync://") || split_remote(endpoint).is_some() && endpoint.contains("::")
+}
+
+/// Whether the trailing-slash choice matters for `src`. Remote paths can't be
+/// checked from here, so they are assumed to be directories.
+fn is_directory_like(src: &str) -> bool {
+    let src = src.trim();
+    !src.is_empty()
+        && (src.ends_with('/') || split_remote(src).is_some() || Path::new(src).is_dir())
+}
+
+/// Adds or removes the trailing slash that makes rsync copy a directory's
+/// contents rather than the directory itself. Roots are left alone.
+fn set_trailing_slash(src: &str, contents: bool) -> String {
+    let trimmed = src.trim_end_matches('/');
+    if trimmed.is_empty() || trimmed.ends_with(':') {
+        return src.to_string();
+    }
+    if contents {
+        format!("{}/", trimmed)
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// `--atimes` and `--crtimes`, which -a doesn't cover. Left out where the
+/// local rsync can't do them.
+fn push_extra_time_args(cmd: &mut Command, options: &RsyncOptions, version: Option<&RsyncVersion>) {
+    if options.atimes && flag_supported(version, "--atimes") {
+        cmd.arg("--atimes");
+    }
+    if options.crtimes && crtimes_supported(version) {
+        cmd.arg("--crtimes");
+    }
+}
+
+/// How the files are opened and who by, which decides what the dry-run can read too.
+fn push_access_args(cmd: &mut Command, options: &RsyncOptions, version: Option<&RsyncVersion>) {
+    if options.open_noatime && flag_supported(version, "--open-noatime") {
+        cmd.arg("--open-noatime");
+    }
+    if let Some(user) = options.copy_as()
+        && flag_supported(version, "--copy-as")
+    {
+        cmd.arg(format!("--copy-as={}", user));
+    }
+}
+
+/// Creation times can only be set on macOS.
+fn crtimes_supported(version: Option<&RsyncVersion>) -> bool {
+    cfg!(target_os = "macos") && flag_supported(version, "--crtimes")
+}
+
+fn push_transfer_args(
+    cmd: &mut Command,
+    options: &RsyncOptions,
+    lists: ListPaths,
+    version: Option<&RsyncVersion>,
+) {
+    apply_env(cmd, options);
+
+    if let Some(rsh) = custom_rsh(options) {
+        cmd.arg("-e").arg(rsh);
+    } else if options.force_ipv.is_some() || options.use_ssh_multiplexing {
+        cmd.arg("-e").arg(ssh_command(options));
+    }
+    push_ip_version_args(cmd, options);
+    push_rsync_path_arg(cmd, options);
+
+    if let Some(address) = bind_address(options) {
+        cmd.arg(format!("--address={}", address));
+    }
+
+    if options.archive {
+        cmd.arg("-a");
+    } else {
+        if options.recursive {
+            cmd.arg("-r");
+        }
+        if options.symlinks {
+            cmd.arg("-l");
+        }
+        if options.permissions {
+            cmd.arg("-p");
+        }
+        if options.time {
+            cmd.arg("-t");
+        }
+        if options.group {
+            cmd.arg("-g");
+        }
+    }
+    push_link_args(cmd, options);
+    push_extra_time_args(cmd, options, version);
+    push_access_args(cmd, options, version);
+
+    if options.compress {
+        cmd.arg("-z");
+        if let Some(suffixes) = options.skip_compress.as_deref().map(str::trim)
+            && !suffixes.is_empty()
+            && flag_supported(version, "--skip-compress")
+        {
+            cmd.arg(format!("--skip-compress={}", suffixes));
+        }
+    }
+
+    if options.is_dry_run() {
+        cmd.arg("-n");
+    }
+
+    if options.checksum {
+        cmd.arg("-c");
+    }
+    push_update_args(cmd, options);
+
+    if options.super_mode {
+        cmd.arg("--super");
+    }
+
+    if options.move_files {
+        cmd.arg("--remove-source-files");
+        // Makes rsync log every removed source file so they can be counted.
+        if flag_supported(version, "--info=remove1") {
+            cmd.arg("--info=remove1");
+        }
+    }
+
+    if options.partial {
+        match options.partial_dir() {
+            Some(dir) => cmd.arg(format!("--partial-dir={}", dir)),
+            None => cmd.arg("--partial"),
+        };
+    }
+
+    if options.delete {
+        cmd.arg("--delete");
+        // Older rsync falls back to its default timing.
+        let timing = options
+            .delete_timing
+            .as_arg()
+            .filter(|x| flag_supported(version, x));
+        if let Some(timing) = timing {
+            cmd.arg(timing);
+        }
+        // --delete-delay only defers deletions if the updates are deferred too.
+        if timing == Some("--delete-delay") {
+            cmd.arg("--delay-updates");
+        }
+    }
+
+    if options.limit_bw {
+        cmd.arg(format!("--bwlimit={}", options.bwlimit_kbps));
+    }
+
+    push_selection_args(cmd, options, lists);
+
+    cmd.arg(&options.src);
+    cmd.arg(&options.dest);
+}
+
+/// These change how links on either side are treated, so the dry-run and
+/// compare need them too to see the same files as the transfer.
+fn push_link_args(cmd: &mut Command, options: &RsyncOptions) {
+    if options.copy_links {
+        cmd.arg("-L");
+    }
+    if options.copy_unsafe_links {
+        cmd.arg("--copy-unsafe-links");
+    }
+    if options.keep_dirlinks {
+        cmd.arg("-K");
+    }
+}
+
+/// These decide which files get sent at all, so the dry-run counts need them too.
+fn push_update_args(cmd: &mut Command, options: &RsyncOptions) {
+    if options.update_only {
+        cmd.arg("-u");
+    }
+    if let Some(dir) = options.link_dest() {
+        cmd.arg(format!("--link-dest={}", dir));
+    }
+}
+
+/// Which files take part: the exclude/include patterns and the file list.
+/// Shared with the dry-run so its counts match the real transfer.
+fn push_selection_args(cmd: &mut Command, options: &RsyncOptions, lists: ListPaths) {
+    // These change where files land, so the dry-run's itemized paths need them too.
+    if options.relative {
+        cmd.arg("-R");
+    }
+    if options.no_implied_dirs {
+        cmd.arg("--no-implied-dirs");
+    }
+
+    for rule in &options.filter_rules {
+        if !rule.pattern.is_empty() {
+            cmd.arg(rule.kind.as_arg()).arg(&rule.pattern);
+        }
+    }
+
+    if let Some(path) = lists.exclude_from {
+        cmd.arg(format!("--exclude-from={}", path.display()));
+    }
+
+    // --files-from implies --relative but turns off the recursion -a would add.
+    if let Some(path) = lists.files_from {
+        cmd.arg(format!("--files-from={}", path.display()));
+        cmd.arg("-r");
+    }
+}
+
+fn create_rsync_dry_run_command(
+    options: &RsyncOptions,
+    lists: ListPaths,
+    version: Option<&RsyncVersion>,
+) -> Command {
+    let mut cmd = Command::new(options.rsync_binary());
+    apply_env(&mut cmd, options);
+
+    match custom_rsh(options) {
+        Some(rsh) => cmd.arg("-e").arg(rsh),
+        None => cmd.arg("-e").arg(format!(
+            "{} -o PasswordAuthentication=no -o PreferredAuthentications=publickey",
+            ssh_command(options)
+        )),
+    };
+    push_ip_version_args(&mut cmd, options);
+    push_rsync_path_arg(&mut cmd, options);
+    cmd.arg("-an");
+    push_link_args(&mut cmd, options);
+    push_extra_time_args(&mut cmd, options, version);
+    push_access_args(&mut cmd, options, version);
+    push_update_args(&mut cmd, options);
+    cmd.arg("--stats");
+
+    // Itemize so the changes and planned deletions can be listed before the run.
+    cmd.arg("--out-format=%i %l %n%L");
+    if options.delete {
+        cmd.arg("--delete");
+    }
+
+    if options.human_readable {
+        cmd.arg("-h");
+    }
+
+    // --address only affects daemon connections, which the dry-run opens too.
+    if (is_daemon_endpoint(&options.src) || is_daemon_endpoint(&options.dest))
+        && let Some(address) = bind_address(options)
+    {
+        cmd.arg(format!("--address={}", address));
+    }
+
+    push_selection_args(&mut cmd, options, lists);
+
+    cmd.arg(&options.src);
+    cmd.arg(&options.dest);
+
+    cmd
+}
+
+/// Lists what differs between the endpoints without transferring anything.
+fn create_rsync_compare_command(options: &RsyncOptions, lists: ListPaths) -> Command {
+    let mut cmd = Command::new(options.rsync_binary());
+    apply_env(&mut cmd, options);
+
+    match custom_rsh(options) {
+        Some(rsh) => cmd.arg("-e").arg(rsh),
+        None => cmd.arg("-e").arg(format!(
+            "{} -o PasswordAuthentication=no -o PreferredAuthentications=publickey",
+            ssh_command(options)
+        )),
+    };
+    push_ip_version_args(&mut cmd, options);
+    push_rsync_path_arg(&mut cmd, options);
+    cmd.args(["-rin", "--delete", "--out-format=%i %l %n%L"]);
+    push_link_args(&mut cmd, options);
+    push_selection_args(&mut cmd, options, lists);
+
+    cmd.arg(&options.src);
+    cmd.arg(&options.dest);
+
+    cmd
+}
+
+/// Runs the compare command, keeping `lists` alive until it is done.
+fn compare_endpoints(options: &RsyncOptions, lists: ListFiles) -> Result<Comparison, String> {
+    let output = create_rsync_compare_command(options, lists.paths())
+        .output()
+        .map_err(|e| format!("Failed to run rsync: {}", e))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(match known_error_hint(&stderr) {
+            Some(hint) => format!("{}\n{}", stderr.trim(), hint),
+            None => stderr.trim().to_string(),
+        });
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(Comparison::from_items(
+        stdout.lines().filter_map(parse_itemized_line),
+    ))
+}
+
+/// Paths of the list files passed to rsync, borrowed from `ListFiles`.
+#[derive(Default, Clone, Copy)]
+struct ListPaths<'a> {
+    exclude_from: Option<&'a Path>,
+    files_from: Option<&'a Path>,
+}
+
+/// Temporary files written from the inline editors for --exclude-from and
+/// --files-from. They are removed when this is dropped.
+#[derive(Default)]
+struct ListFiles {
+    exclude_from: Option<NamedTempFile>,
+    files_from: Option<NamedTempFile>,
+    /// A list file the user already had, used as is.
+    files_from_existing: Option<PathBuf>,
+}
+
+fn write_temp_file(content: &str, what: &str) -> anyhow::Result<NamedTempFile> {
+    let mut file =
+        NamedTempFile::new().with_context(|| format!("Failed to create the {}", what))?;
+    file.write_all(content.as_bytes())
+        .and_then(|_| file.flush())
+        .with_context(|| format!("Failed to write the {}", what))?;
+
+    Ok(file)
+}
+
+impl ListFiles {
+    fn write(options: &RsyncOptions) -> anyhow::Result<ListFiles> {
+        let mut lists = ListFiles::default();
+
+        if !options.exclude_from_content.trim().is_empty() {
+            lists.exclude_from = Some(write_temp_file(
+                &options.exclude_from_content,
+                "exclude file",
+            )?);
+        }
+
+        if options.files_from {
+            match options.files_from_file.trim() {
+                "" => {
+                    let mut content = file_list_entries(options).join("\n");
+                    content.push('\n');
+                    lists.files_from = Some(write_temp_file(&content, "file list")?);
+                }
+                x => lists.files_from_existing = Some(PathBuf::from(x)),
+            }
+        }
+
+        Ok(lists)
+    }
+
+    fn paths(&self) -> ListPaths<'_> {
+        ListPaths {
+            exclude_from: self.exclude_from.as_ref().map(|x| x.path()),
+            files_from: self
+                .files_from
+                .as_ref()
+                .map(|x| x.path())
+                .or(self.files_from_existing.as_deref()),
+        }
+    }
+}
+
+/// The inline file list without blank lines and `#` comments. Absolute paths
+/// under the source are made relative to it, as --files-from expects.
+fn file_list_entries(options: &RsyncOptions) -> Vec<String> {
+    let src = Path::new(options.src.trim());
+    options
+        .files_from_list
+        .lines()
+        .map(str::trim)
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        .map(|x| match Path::new(x).strip_prefix(src) {
+            Ok(relative) if src.is_absolute() => relative.to_string_lossy().to_string(),
+            _ => x.to_string(),
+        })
+        .collect()
+}
+
+/// Tees a line into the raw log, reporting the first write failure as an error line.
+fn tee_raw_log(
+    raw_log: &Option<Arc<Mutex<RawLog>>>,
+    line: &str,
+    stderr: bool,
+    tx: &mpsc::Sender<StateMessage>,
+) {
+    if let Some(raw_log) = raw_log
+        && let Err(e) = raw_log.lock().unwrap().write_line(line, stderr)
+    {
+        tx.send(StateMessage::Error(Error {
+            line: format!("{:#}", e),
+        }))
+        .unwrap();
+    }
+}
+
+/// A pseudo-terminal for rsync's stdout: the master end to read from and the
+/// slave end to hand to the child.
+fn open_pty() -> Option<(File, OwnedFd)> {
+    let pty = nix::pty::openpty(None, None).ok()?;
+    Some((File::from(pty.master), pty.slave))
+}
+
+/// `files_count` and `deletions_count` come from the dry-run and only serve
+/// as the denominator of the overall progress, which both kinds of lines advance.
+/// A `files_count` of 0 means there was no dry-run. With `use_pty` rsync writes
+/// to a pseudo-terminal, falling back to a pipe if none can be opened.
+fn run_rsync(
+    mut cmd: Command,
+    files_count: u64,
+    deletions_count: u64,
+    use_pty: bool,
+    raw_log: Option<Arc<Mutex<RawLog>>>,
+    ctx: egui::Context,
+) -> std::io::Result<(Receiver<StateMessage>, u32)> {
+    let (tx, rx) = mpsc::channel::<StateMessage>();
+    let total = (files_count + deletions_count).max(1) as f32;
+
+    let (pty_master, stdout) = match use_pty.then(open_pty).flatten() {
+        Some((master, slave)) => (Some(master), Stdio::from(slave)),
+        None => (None, Stdio::piped()),
+    };
+    let mut child = cmd.stdout(stdout).stderr(Stdio::piped()).spawn()?;
+    // Our copy of the slave end has to go, or reading the master never ends.
+    drop(cmd);
+    let pid = child.id();
+    let stdout: Box<dyn Read + Send> = match pty_master {
+        Some(master) => Box::new(master),
+        None => Box::new(child.stdout.take().unwrap()),
+    };
+    let stderr = child.stderr.take().unwrap();
+    let mut reader = BufReader::new(stdout);
+    let err_reader = BufReader::new(stderr);
+    let mut buffer = Vec::new();
+
+    let cloned_tx = tx.clone();
+    let err_raw_log = raw_log.clone();
+
+    let err_thread = thread::spawn(move || {
+        for line in err_reader.lines().map_while(Result::ok) {
+            tee_raw_log(&err_raw_log, &line, true, &cloned_tx);
+            cloned_tx.send(StateMessage::Raw(line.clone())).unwrap();
+            cloned_tx.send(StateMessage::Error(Error { line })).unwrap();
+        }
+    });
+
+    thread::spawn(move || {
+        let mut count = 0;
+        let mut deleted = 0;
+        let mut checked = None;
+        // Everything from "Number of files:" on is the --stats summary.
+        let mut stats: Option<String> = None;
+
+        while let Ok(bytes_read) = reader.read_until(b'\r', &mut buffer) {
+            if bytes_read == 0 {
+                break;
+            }
+
+            if let Ok(line_str) = str::from_utf8(&buffer) {
+                let trimmed_line = line_str.trim_end_matches(['\r', '\n']).trim();
+                let lines = trimmed_line.lines();
+
+                for line in lines {
+                    tee_raw_log(&raw_log, line, false, &tx);
+                    tx.send(StateMessage::Raw(line.to_string())).unwrap();
+
+                    if line.starts_with("Number of files:") {
+                        stats = Some(String::new());
+                    }
+                    if let Some(stats) = &mut stats {
+                        stats.push_str(line);
+                        stats.push('\n');
+                        continue;
+                    }
+
+                    let p = parse_rsync_progress(line);
+                    if let Some(progress) = p {
+                        // rsync's own check counter follows incremental recursion,
+                        // so it beats the dry-run estimate whenever it is present.
+                        if let Some(fraction) = progress.checked_fraction() {
+                            checked = Some(fraction);
+                        }
+                        let total_progress = match checked {
+                            // Fast start has no file count, only rsync's own counter.
+                            Some(fraction) if files_count == 0 => fraction,
+                            Some(fraction) => {
+                                (fraction * files_count as f32 + deleted as f32) / total
+                            }
+                            None => (count + deleted) as f32 / total,
+                        };
+
+                        tx.send(StateMessage::Progress(Progress {
+                            progress: progress.percentage as f32 / 100.0,
+                            total_progress,
+                            checked: progress.checked(),
+                            speed_bps: parse_speed_bps(&progress.speed).unwrap_or_default(),
+                            speed: progress.speed,
+                            time: progress.estimated_time,
+                            bytes_sent: progress.bytes_transferred,
+                        }))
+                        .unwrap();
+
+                        ctx.request_repaint();
+                    }
+
+                    if line.starts_with(['>', '<']) {
+                        count += 1;
+
+                        // Names can contain spaces, so take everything after the flags and size.
+                        let next = match parse_itemized_line(line) {
+                            Some(item) => NextFile {
+                                line: item.path,
+                                size: item.size,
+                            },
+                            None => NextFile {
+                                line: line.split_once(' ').map_or(line, |x| x.1).to_string(),
+                                size: 0,
+                            },
+                        };
+                        tx.send(StateMessage::NextFile(next)).unwrap();
+
+                        ctx.request_repaint();
+                    } else if line.starts_with(".f") {
+                        // Unchanged files are only itemized by the verify pass (-ii).
+                        count += 1;
+
+                        tx.send(StateMessage::Progress(Progress {
+                            total_progress: count as f32 / total,
+                            ..Default::default()
+                        }))
+                        .unwrap();
+
+                        ctx.request_repaint();
+                    } else if line.starts_with("*deleting") {
+                        deleted += 1;
+
+                        let done = checked.map_or(count as f32, |x| x * files_count as f32);
+                        tx.send(StateMessage::Deleted((done + deleted as f32) / total))
+                            .unwrap();
+
+                        ctx.request_repaint();
+                    }
+                    if let Some(item) = parse_itemized_line(line) {
+                        tx.send(StateMessage::Itemized(item)).unwrap();
+                    }
+
+                    if line.starts_with("sender removed ") {
+                        tx.send(StateMessage::SourceRemoved(Default::default()))
+                            .unwrap();
+                    }
+                }
+            }
+
+            buffer.clear();
+        }
+
+        // rsync may close stdout early and keep writing the reason to stderr,
+        // so the run only counts as finished once both streams are drained.
+        let _ = err_thread.join();
+        // EOF only means rsync closed stdout; the exit code tells whether it succeeded.
+        let exit_code = child.wait().ok().and_then(|x| x.code()).unwrap_or(-1);
+        if let Some(raw_log) = &raw_log {
+            raw_log.lock().unwrap().flush();
+        }
+        if let Some(stats) = stats {
+            tx.send(StateMessage::Stats(stats)).unwrap();
+        }
+        tx.send(StateMessage::FinishedWithStatus(exit_code))
+            .unwrap();
+        ctx.request_repaint();
+    });
+
+    Ok((rx, pid))
+}
+
+/// Shows a "▾" button listing recently used paths. Returns true when `target`
+/// was filled from the list.
+fn recent_paths_menu(ui: &mut egui::Ui, recent: &mut Vec<String>, target: &mut String) -> bool {
+    let mut filled = false;
+
+    ui.add_enabled_ui(!recent.is_empty(), |ui| {
+        ui.menu_button("▾", |ui| {
+            let mut removed = None;
+            for (i, path) in recent.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("✖").clicked() {
+                        removed = Some(i);
+                    }
+                    if ui.button(path).clicked() {
+                        *target = path.clone();
+                        filled = true;
+                        ui.close();
+                    }
+                });
+            }
+            if let Some(i) = removed {
+                recent.remove(i);
+            }
+        });
+    });
+
+    filled
+}
+
+/// A gentle attention request on success and a critical one on failure,
+/// with the desktop's sound for the event where a player for it is installed.
+fn completion_cue(ctx: &egui::Context, succeeded: bool) {
+    let attention = if succeeded {
+        egui::UserAttentionType::Informational
+    } else {
+        egui::UserAttentionType::Critical
+    };
+    ctx.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(attention));
+
+    let (program, sound) = match (cfg!(target_os = "macos"), succeeded) {
+        (true, true) => ("afplay", "/System/Library/Sounds/Glass.aiff"),
+        (true, false) => ("afplay", "/System/Library/Sounds/Basso.aiff"),
+        (false, true) => ("canberra-gtk-play", "--id=complete"),
+        (false, false) => ("canberra-gtk-play", "--id=dialog-error"),
+    };
+    if find_in_path(program).is_some() {
+        // Waited for on a thread so the player doesn't linger as a zombie.
+        thread::spawn(move || {
+            let _ = Command::new(program)
+                .arg(sound)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+        });
+    }
+}
+
+fn open_in_file_manager(path: &Path) -> anyhow::Result<()> {
+    if !path.exists() {
+        anyhow::bail!("The destination {} does not exist", path.display());
+    }
+
+    let program = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(target_os = "windows") {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+
+    Command::new(program)
+        .arg(path)
+        .spawn()
+        .with_context(|| format!("Failed to open {} with {}", path.display(), program))?;
+
+    Ok(())
+}
+
+/// `[user@]host` of the first endpoint reached over SSH, if any.
+fn ssh_target(options: &RsyncOptions) -> Option<&str> {
+    [&options.src, &options.dest]
+        .into_iter()
+        .map(|x| x.trim())
+        .find(|x| split_remote(x).is_some() && !is_daemon_endpoint(x))
+        .and_then(|x| x.split_once(':'))
+        .map(|(target, _)| target)
+}
+
+/// Checks that `target` accepts key-based SSH logins, the way the dry-run connects.
+fn test_ssh_connection(options: &RsyncOptions, target: &str) -> Result<(), String> {
+    let mut cmd = Command::new("ssh");
+    match options.force_ipv {
+        Some(IpVersion::V4) => {
+            cmd.arg("-4");
+        }
+        Some(IpVersion::V6) => {
+            cmd.arg("-6");
+        }
+        None => {}
+    }
+    apply_env(&mut cmd, options);
+    cmd.args([
+        "-o",
+        "BatchMode=yes",
+        "-o",
+        "ConnectTimeout=5",
+        target,
+        "true",
+    ]);
+
+    let output = cmd
+        .output()
+        .map_err(|e| format!("Failed to run ssh: {}", e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match stderr.lines().map(str::trim).rfind(|x| !x.is_empty()) {
+        Some(line) => Err(line.to_string()),
+        None => Err(format!("ssh exited with {}", output.status)),
+    }
+}
+
+/// rsync exits with 23 (partial transfer) or 3 (file selection error) when
+/// the source can't be stat'ed or entered. Only errors about `src` itself
+/// count, not the receiver failing on the destination or a single missing
+/// --files-from entry.
+fn is_missing_source(exit_code: Option<i32>, stderr: &str, src: &str) -> bool {
+    let src = split_remote(src).map_or(src, |(_, path)| path);
+    let src = src.trim_end_matches('/');
+
+    matches!(exit_code, Some(3) | Some(23))
+        && stderr.lines().any(|x| {
+            // rsync 3.2 tags the side that failed; untagged lines come from the sender.
+            let sender = !["[Receiver]", "[receiver]", "[generator]"]
+                .iter()
+                .any(|tag| x.contains(tag));
+            let path = x
+                .split('"')
+                .nth(1)
+                .unwrap_or_default()
+                .trim_end_matches('/');
+            sender
+                && (x.contains("link_stat") || x.contains("change_dir"))
+                && x.contains("No such file or directory")
+                && (src.is_empty() || path == src || path.ends_with(&format!("/{}", src)))
+        })
+}
+
+/// ssh exits with 255 when it can't reach the host; rsync passes that through.
+fn is_connection_failure(exit_code: Option<i32>, stderr: &str) -> bool {
+    const PATTERNS: [&str; 6] = [
+        "Connection refused",
+        "Connection timed out",
+        "Connection reset",
+        "No route to host",
+        "Network is unreachable",
+        "Could not resolve hostname",
+    ];
+
+    exit_code == Some(255) || PATTERNS.iter().any(|x| stderr.contains(x))
+}
+
+/// Share of the destination's files above which a run counts as a mass deletion.
+const MASS_DELETION_FRACTION: f64 = 0.5;
+
+/// Deleted directories are listed with a trailing slash.
+fn deleted_files(deletions: &[String]) -> u64 {
+    deletions.iter().filter(|x| !x.ends_with('/')).count() as u64
+}
+
+/// Moves `items[from]` so it ends up in front of what is now `items[to]`;
+/// `to == items.len()` moves it to the end.
+fn move_item<T>(items: &mut [T], from: usize, to: usize) {
+    if to > from + 1 {
+        items[from..to].rotate_left(1);
+    } else if to < from {
+        items[to..=from].rotate_right(1);
+    }
+}
+
+/// A "?" that shows `help` on hover.
+fn help_icon(ui: &mut egui::Ui, help: &str) {
+    ui.label(egui::RichText::new("?").weak())
+        .on_hover_text(help);
+}
+
+/// A checkbox with a help icon after it. The response is the checkbox's.
+fn flag_checkbox(
+    ui: &mut egui::Ui,
+    enabled: bool,
+    value: &mut bool,
+    label: &str,
+    help: &str,
+) -> egui::Response {
+    ui.horizontal(|ui| {
+        let response = ui.add_enabled(enabled, Checkbox::new(value, label));
+        help_icon(ui, help);
+        response
+    })
+    .inner
+}
+
+/// What the last dry-run would send, and how long that took on average before.
+fn transfer_estimate_ui(ui: &mut egui::Ui, bytes: Option<u64>, history: &History) {
+    let Some(bytes) = bytes else {
+        return;
+    };
+
+    ui.label(egui::RichText::new(format!("Estimated transfer: {}", format_bytes(bytes))).strong());
+    if let Some(bps) = history.average_speed_bps() {
+        ui.label(format!(
+            "Estimated time: {} (based on your average of {}/s)",
+            format_rough_duration((bytes as f64 / bps) as u64),
+            format_bytes(bps as u64)
+        ));
+    }
+}
+
+/// "Total transferred file size" from a dry-run's --stats.
+fn planned_bytes(stats: &HashMap<String, String>) -> Option<u64> {
+    stats
+        .get("Total transferred file size")
+        .and_then(|x| parse_human_number(x.trim_end_matches(" bytes")))
+}
+
+/// Files and bytes in the source as counted by a dry-run's --stats.
+fn dry_run_estimate(stats: &HashMap<String, String>) -> Option<SourceEstimate> {
+    Some(SourceEstimate {
+        files: stats
+            .get("Number of files (total)")
+            .and_then(|x| parse_rsync_number(x))?,
+        bytes: stats
+            .get("Total file size")
+            .and_then(|x| parse_human_number(x.trim_end_matches(" bytes")))?,
+        filtered: true,
+    })
+}
+
+fn parse_rsync_stats(lines: &str) -> HashMap<String, String> {
+    let mut stats: HashMap<String, String> = HashMap::new();
+
+    lazy_static! {
+        static ref RE_KEY_VALUE: Regex = Regex::new(r"^(.+?):\s*(.*)$").unwrap();
+        static ref RE_FILE_KIND: Regex = Regex::new(r"(\w+):\s*(\d+(?:[.,]\d+)*)").unwrap();
+        static ref RE_TOTAL_SPEEDUP: Regex = Regex::new(
+            r"(?i)total size is ([\d.,]+[KMGTP]?)\s+speedup is ([\d.,]+)(?:\s+\((.*)\))?"
+        )
+        .unwrap();
+        static ref RE_HUMAN_SIZE: Regex =
+            Regex::new(r"(?i)^([\d.,]+[KMGTP])(\s+bytes.*)?$").unwrap();
+    }
+
+    for line in lines.lines() {
+        let trimmed_line = line.trim();
+        if trimmed_line.is_empty() {
+            continue;
+        }
+
+        if let Some(caps) = RE_KEY_VALUE.captures(trimmed_line) {
+            let key = caps.get(1).unwrap().as_str().trim().to_string();
+            let value = caps.get(2).unwrap().as_str().trim().to_string();
+
+            if key == "Number of files" {
+                // e.g. "3 (reg: 2, dir: 1)", "1 (dir: 1)" for an empty directory, or just "0".
+                let total = value.split_whitespace().next().unwrap_or_default();
+                stats.insert("Number of files (total)".to_string(), total.to_string());
+                for (name, label) in [
+                    ("reg", "regular"),
+                    ("dir", "directories"),
+                    ("link", "links"),
+                ] {
+                    let count = RE_FILE_KIND
+                        .captures_iter(&value)
+                        .find(|x| &x[1] == name)
+                        .map(|x| x[2].to_string())
+                        .unwrap_or_else(|| "0".to_string());
+                    stats.insert(format!("Number of files ({})", label), count);
+                }
+            } else {
+                // With --human-readable the sizes carry unit suffixes; store them as raw bytes.
+                let value = match RE_HUMAN_SIZE.captures(&value) {
+                    Some(caps) => format!(
+                        "{}{}",
+                        parse_human_number(&caps[1]).unwrap_or_default(),
+                        caps.get(2).map(|x| x.as_str()).unwrap_or_default()
+                    ),
+                    None => value,
+                };
+                stats.insert(key, value);
+            }
+        } else if let Some(caps) = RE_TOTAL_SPEEDUP.captures(trimmed_line) {
+            stats.insert(
+                "Total size (summary)".to_string(),
+                parse_human_number(caps.get(1).unwrap().as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            );
+            stats.insert(
+                "Speedup".to_string(),
+                caps.get(2).unwrap().as_str().to_string(),
+            );
+            // Only dry runs add "(DRY RUN)" after the speedup.
+            if let Some(run_type) = caps.get(3) {
+                stats.insert("Run type".to_string(), run_type.as_str().to_string());
+            }
+        }
+    }
+
+    stats
+}
+
+/// Speedup reported by rsync and the share of the total size that was sent
+/// as literal data, from `--stats` output.
+fn delta_efficiency(stats: &HashMap<String, String>) -> Option<(f64, f64)> {
+    let bytes = |key: &str| {
+        stats
+            .get(key)
+            .and_then(|x| parse_human_number(x.trim_end_matches(" bytes")))
+    };
+
+    // Some locales print the speedup as "8,42".
+    let speedup = stats.get("Speedup")?;
+    let speedup = if speedup.contains('.') {
+        speedup.replace(',', "")
+    } else {
+        speedup.replace(',', ".")
+    };
+    let speedup: f64 = speedup.parse().ok()?;
+    let literal = bytes("Literal data")?;
+    let total = bytes("Total file size").or_else(|| bytes("Total size (summary)"))?;
+    if total == 0 {
+        return None;
+    }
+
+    Some((speedup, literal as f64 / total as f64))
+}
+
+impl RunInfo {
+    /// Time left for the bytes the dry-run planned, so a fast start doesn't
+    /// promise more than --bwlimit allows.
+    fn overall_eta(&self) -> Option<String> {
+        let planned = planned_bytes(&self.stats)?;
+        let remaining = planned.saturating_sub(self.bytes.total());
+        let observed = (self.speed_samples > 0).then(|| self.speed_sum / self.speed_samples as f64);
+        let cap = self
+            .options
+            .limit_bw
+            .then_some(self.options.bwlimit_kbps as f64 * 1024.0);
+
+        let (secs, capped) = estimate_eta(remaining, observed, cap)?;
+        let mut text = format!("Overall ETA: {}", format_duration(secs));
+        if capped && let Some(cap) = cap {
+            text.push_str(&format!(" (capped at {}/s)", format_bytes(cap as u64)));
+        }
+        Some(text)
+    }
+}
+
+impl AppState {
+    fn autosave_draft(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.viewport().close_requested()) {
+            draft::clear();
+            return;
+        }
+        if self.restore_draft.is_some() {
+            return;
+        }
+
+        let snapshot = serde_json::to_string(&self.options).unwrap_or_default();
+        if self.draft_snapshot.is_empty() {
+            self.draft_snapshot = snapshot;
+        } else if snapshot != self.draft_snapshot {
+            self.draft_snapshot = snapshot;
+            self.draft_changed_at = Some(Instant::now());
+        }
+
+        if let Some(changed_at) = self.draft_changed_at {
+            if changed_at.elapsed() >= DRAFT_DEBOUNCE {
+                self.draft_changed_at = None;
+                if let Err(e) = draft::save(&self.options) {
+                    self.error_logs.push_str(&format!("{:#}\n", e));
+                }
+            } else {
+                ctx.request_repaint_after(DRAFT_DEBOUNCE - changed_at.elapsed());
+            }
+        }
+    }
+
+    /// Re-runs `rsync --version` on a background thread when the configured
+    /// binary changed, but not while it is still being typed.
+    fn refresh_rsync_version(&mut self, ctx: &egui::Context) {
+        if let Some(rx) = &self.rsync_version_rx
+            && let Ok(version) = rx.try_recv()
+        {
+            self.rsync_version = version;
+            self.rsync_version_rx = None;
+        }
+
+        let binary = self.options.rsync_binary().to_string();
+        if self.editing_rsync_binary || self.rsync_version_for.as_deref() == Some(binary.as_str()) {
+            return;
+        }
+
+        // Replacing the receiver drops the answer of a detection still running
+        // for a binary that was typed over.
+        let (tx, rx) = mpsc::channel();
+        let ctx = ctx.clone();
+        self.rsync_version_for = Some(binary.clone());
+        thread::spawn(move || {
+            let _ = tx.send(RsyncVersion::detect(&binary));
+            ctx.request_repaint();
+        });
+        self.rsync_version_rx = Some(rx);
+    }
+
+    fn finish_run(&mut self, ctx: &egui::Context, exit_code: i32) {
+        self.child_pid = None;
+        self.confirm_cancel = false;
+        self.paused = false;
+        let exit_code = Some(exit_code);
+
+        let Some(run) = &mut self.run else {
+            self.is_finished = true;
+            return;
+        };
+
+        match self.phase {
+            Phase::Transfer => {
+                run.exit_code = exit_code;
+                self.exit_code = exit_code;
+
+                if exit_code != Some(0)
+                    && !self.cancelled
+                    && let Some(hint) = known_error_hint(&self.error_logs.to_string())
+                {
+                    self.error_logs.push_str(hint);
+                    self.error_logs.push('\n');
+                }
+
+                // The last file never sees a next one to mark it done.
+                if exit_code == Some(0) && !self.overall_progress && !run.options.is_dry_run() {
+                    run.bytes.finish();
+                }
+
+                if run.speed_samples > 0 {
+                    let average = run.speed_sum / run.speed_samples as f64;
+                    self.logs.push(LogLine::new(
+                        LogKind::Info,
+                        format!(
+                            "Average rate reported by rsync: {}/s",
+                            format_bytes(average as u64)
+                        ),
+                    ));
+                }
+
+                let bytes = run.bytes.total();
+                let elapsed = run.started.elapsed().as_secs_f64();
+                let mut summary = format!(
+                    "Transferred {} ({} files) in {} at an average of {}/s",
+                    format_bytes(bytes),
+                    format_count(run.files_transferred),
+                    format_duration(elapsed as u64),
+                    format_bytes((bytes as f64 / elapsed.max(1.0)) as u64),
+                );
+                if let Some(raw_log) = &run.raw_log {
+                    summary.push_str(&format!(
+                        "\nRaw output: {}",
+                        raw_log.lock().unwrap().path().display()
+                    ));
+                }
+                self.summary = Some(summary);
+
+                // rsync's own totals include the file list and protocol overhead.
+                // A cancelled run prints no totals, but what it sent still counts.
+                if !run.options.is_dry_run() {
+                    let traffic = ["Total bytes sent", "Total bytes received"]
+                        .iter()
+                        .map(|key| {
+                            self.final_stats
+                                .get(*key)
+                                .and_then(|x| parse_human_number(x.trim_end_matches(" bytes")))
+                        })
+                        .sum::<Option<u64>>()
+                        .unwrap_or(bytes);
+                    self.config.usage.add(traffic);
+                    if let Err(e) = self.config.save() {
+                        self.error_logs.push_str(&format!("{:#}\n", e));
+                    }
+                }
+
+                if run.options.move_files {
+                    let notice = if run.options.is_dry_run() {
+                        "Dry run — no source files were removed".to_string()
+                    } else {
+                        format!("Removed {} source files", self.removed_count)
+                    };
+                    self.logs.push(LogLine::new(LogKind::Info, notice.clone()));
+                    self.notices.push(notice);
+                }
+
+                if exit_code == Some(0)
+                    && !self.cancelled
+                    && run.options.verify
+                    && !run.options.is_dry_run()
+                    && !run.options.move_files
+                {
+                    self.spot_check = None;
+                    let sample = run
+                        .options
+                        .verify_sample
+                        .filter(|_| run.options.spot_check_supported())
+                        .map(|n| random_sample(self.file_log.transferred_files(), n as usize));
+
+                    let command = match sample {
+                        None => Some(create_rsync_verify_command(
+                            &run.options,
+                            run.lists.paths(),
+                            self.rsync_version.as_ref(),
+                        )),
+                        Some(sample) if sample.is_empty() => {
+                            self.logs.push(LogLine::new(
+                                LogKind::Info,
+                                "No files were transferred, skipping the spot-check",
+                            ));
+                            None
+                        }
+                        Some(sample) => match write_temp_file(
+                            &format!("{}\n", sample.join("\n")),
+                            "spot-check list",
+                        ) {
+                            Ok(list) => {
+                                let command = create_rsync_spot_check_command(
+                                    &run.options,
+                                    list.path(),
+                                    self.rsync_version.as_ref(),
+                                );
+                                self.spot_check = Some(SpotCheck {
+                                    files: sample.len(),
+                                    _list: list,
+                                });
+                                Some(command)
+                            }
+                            Err(e) => {
+                                self.error_logs.push_str(&format!("{:#}\n", e));
+                                None
+                            }
+                        },
+                    };
+
+                    if let Some(mut command) = command {
+                        command.process_group(0);
+                        self.phase = Phase::Verify;
+                        self.verify_diffs.clear();
+                        self.current_progress = Progress::default();
+                        let files_count = match &self.spot_check {
+                            Some(spot_check) => {
+                                self.logs.push(LogLine::new(
+                                    LogKind::Info,
+                                    format!("Spot-checking {} random files...", spot_check.files),
+                                ));
+                                spot_check.files as u64
+                            }
+                            None => {
+                                self.logs.push(LogLine::new(LogKind::Info, "Verifying..."));
+                                run.files_count
+                            }
+                        };
+
+                        match run_rsync(
+                            command,
+                            files_count,
+                            0,
+                            run.options.use_pty,
+                            run.raw_log.clone(),
+                            ctx.clone(),
+                        ) {
+                            Ok(rx) => {
+                                self.progress = Some(rx.0);
+                                self.child_pid = Some(rx.1);
+                                return;
+                            }
+                            Err(e) => {
+                                run.verified = Some(false);
+                                self.error_logs.push_str(&format!(
+                                    "Failed to run {}: {}\n",
+                                    run.options.rsync_binary(),
+                                    e
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+            Phase::Verify => {
+                let passed = exit_code == Some(0) && self.verify_diffs.is_empty();
+                run.verified = Some(passed);
+                self.verify_passed = passed;
+
+                if passed {
+                    self.logs
+                        .push(LogLine::new(LogKind::Info, self.verify_result()));
+                } else if !self.verify_diffs.is_empty() {
+                    self.logs.push(LogLine::new(
+                        LogKind::Error,
+                        format!("{}, these paths still differ:", self.verify_result()),
+                    ));
+                    for path in &self.verify_diffs {
+                        self.logs.push(LogLine::new(LogKind::Error, path.clone()));
+                    }
+                } else {
+                    self.logs.push(LogLine::new(
+                        LogKind::Error,
+                        "Verification could not be completed",
+                    ));
+                }
+            }
+        }
+
+        if let Some(run) = &self.run
+            && run.exit_code == Some(0)
+            && !self.cancelled
+            && run.options.verify_sample_pct > 0
+            && !run.options.is_dry_run()
+            && !run.options.move_files
+        {
+            self.start_hash_check(ctx);
+        }
+
+        if let Some(run) = &self.run {
+            let post_command = run.options.post_command.trim().to_string();
+            let exit_code = run.exit_code.unwrap_or(-1).to_string();
+            if !post_command.is_empty() {
+                self.run_hook(
+                    "post-transfer",
+                    &post_command,
+                    &[("RSYNC_EXIT_CODE", &exit_code)],
+                );
+            }
+        }
+
+        if let Some(run) = &self.run
+            && !self.cancelled
+            && self.config.completion_cue
+            && run.started.elapsed().as_secs() >= self.config.completion_cue_min_secs as u64
+        {
+            let succeeded = run.exit_code == Some(0) && run.verified != Some(false);
+            completion_cue(ctx, succeeded);
+        }
+
+        if let Some(run) = &self.run
+            && run.options.use_ssh_multiplexing
+            && custom_rsh(&run.options).is_none()
+        {
+            close_ssh_masters(&run.options);
+        }
+
+        self.is_finished = true;
+        self.record_history();
+    }
+
+    /// Runs a user hook through `sh -c`, copying its output into the error log.
+    /// Returns false when the hook could not be run or exited with non-zero status.
+    fn run_hook(&mut self, name: &str, command: &str, envs: &[(&str, &str)]) -> bool {
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .envs(envs.iter().copied())
+            .output();
+
+        let output = match output {
+            Ok(x) => x,
+            Err(e) => {
+                self.error_logs
+                    .push_str(&format!("Failed to run the {} command: {}\n", name, e));
+                return false;
+            }
+        };
+
+        for line in String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .chain(String::from_utf8_lossy(&output.stderr).lines())
+        {
+            self.error_logs.push_str(&format!("[{}] {}\n", name, line));
+        }
+
+        if !output.status.success() {
+            self.error_logs.push_str(&format!(
+                "The {} command failed ({})\n",
+                name, output.status
+            ));
+            return false;
+        }
+
+        true
+    }
+
+    fn start_run(&mut self, ctx: &egui::Context) {
+        let profile = self.run_profile.take();
+        self.form_notice = None;
+        self.dry_run_summary = None;
+        self.transfer_estimate = None;
+        self.cancelled = false;
+        self.paused = false;
+        self.scan_pending = false;
+        self.error_logs.clear();
+        self.logs.clear();
+        self.is_finished = false;
+        self.exit_code = None;
+        self.summary = None;
+        self.final_stats.clear();
+        self.current_progress = Progress::default();
+        self.phase = Phase::Transfer;
+        self.hash_check = None;
+        self.removed_count = 0;
+        self.file_log.clear();
+        self.raw_output.clear();
+        self.notices.clear();
+        self.overall_progress = self.options.overall_progress
+            && flag_supported(self.rsync_version.as_ref(), "--info=progress2");
+
+        if self.options.partial
+            && let Some(dir) = self.options.partial_dir()
+            && Path::new(dir).is_absolute()
+        {
+            self.error_logs
+                .push_str("The partial directory must be relative to the destination.\n");
+            return;
+        }
+
+        if !self.options.bind_address.trim().is_empty() && bind_address(&self.options).is_none() {
+            self.error_logs
+                .push_str("The bind address is not a valid IP address.\n");
+            return;
+        }
+
+        if let Some(log_file) = self.options.log_file()
+            && let Err(e) = check_writable(Path::new(log_file))
+        {
+            self.error_logs.push_str(&format!(
+                "Cannot write the rsync log file {}: {}\n",
+                log_file, e
+            ));
+            return;
+        }
+
+        let Some(active) = ActiveTransfer::acquire(&self.options.src, &self.options.dest) else {
+            self.error_logs
+                .push_str("A transfer to this destination is already running.\n");
+            return;
+        };
+
+        let pre_command = self.options.pre_command.trim().to_string();
+        if !pre_command.is_empty() && !self.run_hook("pre-transfer", &pre_command, &[]) {
+            self.error_logs
+                .push_str("Transfer aborted because the pre-transfer command failed.\n");
+            return;
+        }
+
+        // An inline list of absolute paths is transferred relative to their common base.
+        // Only the command gets the base; the form keeps the source as typed.
+        let mut options = self.options.clone();
+        if options.files_from
+            && options.files_from_file.trim().is_empty()
+            && let Some(base) = common_base(options.files_from_list.lines().map(str::trim))
+        {
+            options.src = base.to_string_lossy().to_string();
+        }
+
+        let lists = match ListFiles::write(&options) {
+            Ok(x) => x,
+            Err(e) => {
+                self.error_logs.push_str(&format!("{:#}\n", e));
+                return;
+            }
+        };
+
+        // Deletions are always listed for confirmation first, so only plain copies start right away.
+        if self.options.fast_start && !self.options.delete {
+            self.scan_pending = true;
+            let planned = PlannedRun {
+                options,
+                stats: HashMap::new(),
+                files_count: 0,
+                deletions: Vec::new(),
+                dest_files: 0,
+                mass_deletion_confirmed: false,
+                lists,
+                active,
+                profile,
+            };
+            self.launch_run(ctx, planned);
+            return;
+        }
+
+        let mut dry_run =
+            create_rsync_dry_run_command(&options, lists.paths(), self.rsync_version.as_ref());
+        let output = match dry_run.output() {
+            Ok(x) => x,
+            Err(e) => {
+                self.error_logs.push_str(&format!(
+                    "Failed to run {}: {}\n",
+                    self.options.rsync_binary(),
+                    e
+                ));
+                return;
+            }
+        };
+        let result = String::from_utf8_lossy(&output.stdout).to_string();
+        let result_err = String::from_utf8_lossy(&output.stderr).to_string();
+
+        if is_missing_source(output.status.code(), &result_err, &options.src) {
+            self.error_logs
+                .push_str(&format!("Source path not found: {}\n", options.src));
+            self.focus_source = true;
+            return;
+        }
+
+        if self.options.ssh_retry
+            && custom_rsh(&self.options).is_none()
+            && self.ssh_attempt < self.options.ssh_retry_attempts
+            && is_connection_failure(output.status.code(), &result_err)
+        {
+            let delay = self.options.ssh_retry_delay_secs as u64 * 2u64.pow(self.ssh_attempt);
+            self.ssh_attempt += 1;
+            self.retry_at = Some(Instant::now() + Duration::from_secs(delay));
+            self.error_logs.push_str(&result_err);
+            return;
+        }
+
+        if !result_err.trim().is_empty() {
+            self.error_logs.push_str(&result_err);
+            self.error_logs.push('\n');
+            if result_err.contains("Permission denied") {
+                self.error_logs.push_str("Access denied when connecting to the server via SSH. Please check if your SSH key is configured.\n");
+                return;
+            }
+            if let Some(hint) = known_error_hint(&result_err) {
+                self.error_logs.push_str(hint);
+                self.error_logs.push('\n');
+                return;
+            }
+        }
+
+        // Keep the itemized lines out of the stats.
+        let (itemized, stats_lines): (Vec<_>, Vec<_>) = result
+            .lines()
+            .partition(|x| parse_itemized_line(x).is_some());
+        let itemized = itemized
+            .into_iter()
+            .filter_map(parse_itemized_line)
+            .collect::<Vec<_>>();
+        let summary = DryRunSummary::from_items(&itemized);
+        let deletions = itemized
+            .into_iter()
+            .filter(|x| x.action == ItemAction::Deleted)
+            .map(|x| x.path)
+            .collect::<Vec<_>>();
+
+        let data = parse_rsync_stats(&stats_lines.join("\n"));
+        let number_of_files = data.get("Number of files (regular)");
+        if number_of_files.is_none() {
+            self.error_logs
+                .push_str("Could not determine the file count for the transfer.\n");
+            self.error_logs.push_str(&result);
+            self.error_logs.push('\n');
+            return;
+        }
+
+        let Some(files_count) = parse_rsync_number(number_of_files.unwrap()) else {
+            self.error_logs
+                .push_str("Could not parse the file count for the transfer.\n");
+            return;
+        };
+        if files_count == 0 && deletions.is_empty() {
+            self.form_notice = Some("Source contains no files to transfer".to_string());
+            return;
+        }
+
+        self.dry_run_summary = Some(summary);
+        self.transfer_estimate = planned_bytes(&data);
+        if let Some(estimate) = dry_run_estimate(&data) {
+            self.source_estimate = Some(estimate);
+        }
+        let planned = PlannedRun {
+            options,
+            stats: data,
+            files_count,
+            dest_files: files_count.saturating_sub(summary.new) + deleted_files(&deletions),
+            mass_deletion_confirmed: false,
+            deletions,
+            lists,
+            active,
+            profile,
+        };
+
+        // A dry-run only lists the deletions, so there is nothing to confirm.
+        if planned.deletions.is_empty() || planned.options.is_dry_run() {
+            self.launch_run(ctx, planned);
+        } else if matches!(planned.profile, Some((_, true))) {
+            // Nobody is there to confirm the deletions, so the run fails instead of waiting.
+            self.error_logs.push_str(&format!(
+                "Not run: the dry-run found {} deletions, which have to be confirmed, so scheduled runs don't make them.\n",
+                planned.deletions.len()
+            ));
+        } else {
+            self.planned = Some(planned);
+        }
+    }
+
+    /// Compares the endpoints on a background thread; the result shows up in the compare window.
+    fn start_compare(&mut self, ctx: &egui::Context) {
+        let lists = match ListFiles::write(&self.options) {
+            Ok(x) => x,
+            Err(e) => {
+                self.error_logs.push_str(&format!("{:#}\n", e));
+                return;
+            }
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let options = self.options.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(compare_endpoints(&options, lists));
+            ctx.request_repaint();
+        });
+        self.compare = Some(CompareView {
+            src: self.options.src.clone(),
+            dest: self.options.dest.clone(),
+            result: None,
+        });
+        self.compare_rx = Some(rx);
+    }
+
+    /// Keeps the estimate next to the Run button in step with the source path,
+    /// waiting for the typing to stop before walking it.
+    fn update_source_estimate(&mut self, ctx: &egui::Context) {
+        let src = self.options.src.trim();
+        if src != self.estimate_src {
+            self.estimate_src = src.to_string();
+            self.source_edited_at = Some(Instant::now());
+            self.source_scan = None;
+            self.source_estimate = None;
+        }
+
+        if let Some(scan) = &mut self.source_scan
+            && let Some(result) = scan.poll()
+        {
+            self.source_estimate = result;
+            self.source_scan = None;
+        }
+
+        let Some(edited_at) = self.source_edited_at else {
+            return;
+        };
+        let wait = SCAN_DEBOUNCE.saturating_sub(edited_at.elapsed());
+        if !wait.is_zero() {
+            ctx.request_repaint_after(wait);
+            return;
+        }
+        self.source_edited_at = None;
+
+        // Remote endpoints are only estimated on request, since that needs a dry-run.
+        let dest = self.options.dest.trim();
+        if split_remote(src).is_none()
+            && Path::new(src).exists()
+            && !dest.is_empty()
+            && split_remote(dest).is_none()
+        {
+            self.source_scan = Some(SourceScan::walk(src, ctx));
+        }
+    }
+
+    /// Counts the source with a dry-run, for endpoints that can't be walked from here.
+    fn estimate_with_dry_run(&mut self, ctx: &egui::Context) {
+        let lists = match ListFiles::write(&self.options) {
+            Ok(x) => x,
+            Err(e) => {
+                self.error_logs.push_str(&format!("{:#}\n", e));
+                return;
+            }
+        };
+
+        let mut command =
+            create_rsync_dry_run_command(&self.options, lists.paths(), self.rsync_version.as_ref());
+        let child = match command.stdout(Stdio::piped()).stderr(Stdio::null()).spawn() {
+            Ok(x) => x,
+            Err(e) => {
+                self.error_logs
+                    .push_str(&format!("Failed to run dry-run: {}\n", e));
+                return;
+            }
+        };
+        let parse = move |stdout: &str| {
+            // The list files have to outlive the dry-run.
+            drop(lists);
+            dry_run_estimate(&parse_rsync_stats(stdout))
+        };
+        self.source_scan = Some(SourceScan::dry_run(child, parse, ctx));
+    }
+
+    fn source_estimate_ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        if self.source_scan.is_some() {
+            ui.spinner();
+            ui.weak("Estimating…");
+            if ui
+                .small_button("✖")
+                .on_hover_text("Stop estimating")
+                .clicked()
+            {
+                self.source_scan = None;
+            }
+        } else if let Some(estimate) = &self.source_estimate {
+            estimate.ui(ui);
+        } else if self.source_edited_at.is_none()
+            && !self.options.src.trim().is_empty()
+            && !self.options.dest.trim().is_empty()
+            && ui
+                .small_button("Estimate")
+                .on_hover_text("Count the files to send with a dry-run")
+                .clicked()
+        {
+            self.estimate_with_dry_run(ctx);
+        }
+    }
+
+    /// Compares the SHA-256 hashes of a share of the transferred files on both sides.
+    fn start_hash_check(&mut self, ctx: &egui::Context) {
+        let Some(run) = &self.run else {
+            return;
+        };
+        let options = &run.options;
+        if split_remote(options.src.trim()).is_some()
+            || split_remote(options.dest.trim()).is_some()
+            || !options.spot_check_supported()
+        {
+            self.logs.push(LogLine::new(
+                LogKind::Info,
+                "Hash check skipped: it needs a local source and destination, without -R or a file list",
+            ));
+            return;
+        }
+
+        let files: Vec<_> = self
+            .file_log
+            .transferred_files()
+            .map(str::to_string)
+            .collect();
+        let count = (files.len() * options.verify_sample_pct.min(100) as usize).div_ceil(100);
+        let sample = random_sample(files, count);
+        if sample.is_empty() {
+            return;
+        }
+
+        self.logs.push(LogLine::new(
+            LogKind::Info,
+            format!("Checking SHA-256 hashes of {} files...", sample.len()),
+        ));
+        let src = PathBuf::from(spot_check_source(&options.src));
+        let dest = PathBuf::from(options.dest.trim());
+        self.hash_check = Some(HashCheck::start(src, dest, sample, ctx));
+    }
+
+    /// Runs the dry-run on its own and shows what it would change.
+    fn preview_changes(&mut self) {
+        self.form_notice = None;
+        self.error_logs.clear();
+
+        let lists = match ListFiles::write(&self.options) {
+            Ok(x) => x,
+            Err(e) => {
+                self.error_logs.push_str(&format!("{:#}\n", e));
+                return;
+            }
+        };
+
+        let output = match create_rsync_dry_run_command(
+            &self.options,
+            lists.paths(),
+            self.rsync_version.as_ref(),
+        )
+        .output()
+        {
+            Ok(x) => x,
+            Err(e) => {
+                self.error_logs
+                    .push_str(&format!("Failed to run dry-run: {}\n", e));
+                return;
+            }
+        };
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            self.error_logs.push_str(&stderr);
+            return;
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stats = parse_rsync_stats(&stdout);
+        self.transfer_estimate = planned_bytes(&stats);
+        if let Some(estimate) = dry_run_estimate(&stats) {
+            self.source_estimate = Some(estimate);
+        }
+        let items = stdout
+            .lines()
+            .filter_map(parse_itemized_line)
+            .collect::<Vec<_>>();
+        self.dry_run_summary = Some(DryRunSummary::from_items(&items));
+        self.diff_preview = Some(DiffPreview::new(items));
+    }
+
+    /// Spawns the transfer planned by `start_run`, after any confirmation.
+    fn launch_run(&mut self, ctx: &egui::Context, planned: PlannedRun) {
+        let PlannedRun {
+            options,
+            stats,
+            files_count,
+            deletions,
+            lists,
+            active,
+            profile,
+            ..
+        } = planned;
+        self.transfer_estimate = None;
+
+        let id = new_run_id();
+        self.progress_title = format!(
+            "{} → {} [#{}]",
+            profile
+                .as_ref()
+                .map_or_else(|| endpoint_name(&options.src), |(name, _)| name.as_str()),
+            split_remote(&options.dest)
+                .map_or_else(|| endpoint_name(&options.dest), |(host, _)| host),
+            id
+        );
+
+        let raw_log = match self.config.write_raw_logs.then(|| RawLog::create(&id)) {
+            Some(Ok(x)) => Some(Arc::new(Mutex::new(x))),
+            Some(Err(e)) => {
+                self.error_logs.push_str(&format!("{:#}\n", e));
+                None
+            }
+            None => None,
+        };
+
+        let mut command =
+            create_rsync_command(&options, lists.paths(), self.rsync_version.as_ref());
+        // Its own process group, so pausing also stops the ssh and receiver it starts.
+        command.process_group(0);
+        let use_pty = options.use_pty;
+        self.config.remember_paths(&options.src, &options.dest);
+        self.run = Some(RunInfo {
+            id,
+            started: Instant::now(),
+            started_at: Local::now(),
+            command: format_command(&command, &options),
+            options,
+            stats,
+            files_count,
+            files_transferred: 0,
+            speed_sum: 0.0,
+            speed_samples: 0,
+            max_speed_bps: 0.0,
+            error_count: 0,
+            bytes: ByteCount::default(),
+            exit_code: None,
+            verified: None,
+            lists,
+            raw_log: raw_log.clone(),
+            profile,
+            _active: active,
+        });
+        match run_rsync(
+            command,
+            files_count,
+            deletions.len() as u64,
+            use_pty,
+            raw_log.clone(),
+            ctx.clone(),
+        ) {
+            Ok(rx) => {
+                self.progress = Some(rx.0);
+                self.child_pid = Some(rx.1);
+            }
+            Err(e) => {
+                self.error_logs.push_str(&format!(
+                    "Failed to run {}: {}\n",
+                    self.options.rsync_binary(),
+                    e
+                ));
+                self.run = None;
+                return;
+            }
+        }
+
+        self.save_config();
+    }
+
+    fn symlink_handling_ui(&mut self, ui: &mut egui::Ui) {
+        let options = &mut self.options;
+        let keep = (options.archive || options.symlinks)
+            && !options.copy_links
+            && !options.copy_unsafe_links;
+        let skip = !options.archive
+            && !options.symlinks
+            && !options.copy_links
+            && !options.copy_unsafe_links;
+
+        ui.horizontal(|ui| {
+            let label = if options.archive {
+                "Keep as links (-l, part of -a)"
+            } else {
+                "Keep as links (-l)"
+            };
+            if ui.radio(keep, label).clicked() {
+                options.symlinks = true;
+                options.copy_links = false;
+                options.copy_unsafe_links = false;
+            }
+            help_icon(ui, help_text::LINKS);
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .radio(options.copy_links, "Copy the files they point to (-L)")
+                .clicked()
+            {
+                options.symlinks = false;
+                options.copy_links = true;
+                options.copy_unsafe_links = false;
+            }
+            help_icon(ui, help_text::COPY_LINKS);
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .radio(
+                    options.copy_unsafe_links,
+                    "Copy only links pointing outside the tree (--copy-unsafe-links)",
+                )
+                .clicked()
+            {
+                options.symlinks = false;
+                options.copy_links = false;
+                options.copy_unsafe_links = true;
+            }
+            help_icon(ui, help_text::COPY_UNSAFE_LINKS);
+        });
+        if ui
+            .add_enabled(!options.archive, egui::RadioButton::new(skip, "Skip them"))
+            .clicked()
+        {
+            options.symlinks = false;
+            options.copy_links = false;
+            options.copy_unsafe_links = false;
+        }
+
+        // Profiles saved by hand or by older versions can have several set at once.
+        if [
+            options.symlinks,
+            options.copy_links,
+            options.copy_unsafe_links,
+        ]
+        .iter()
+        .filter(|x| **x)
+        .count()
+            > 1
+        {
+            ui.colored_label(
+                egui::Color32::from_rgb(255, 165, 0),
+                "⚠ -l, -L and --copy-unsafe-links exclude each other; pick one of the above.",
+            );
+        }
+    }
+
+    /// The errors with what is needed to make sense of them, formatted for pasting into a bug report.
+    fn error_report(&self) -> String {
+        let command = match &self.run {
+            Some(run) => run.command.clone(),
+            None => format_command(
+                &preview_command(&self.options, self.rsync_version.as_ref()),
+                &self.options,
+            ),
+        };
+        let rsync = match &self.rsync_version {
+            Some(x) => format!(
+                "{}.{}.{} (protocol {})",
+                x.major, x.minor, x.patch, x.protocol
+            ),
+            None => "unknown".to_string(),
+        };
+
+        let mut report = format!(
+            "r-synced {} on {}\nrsync {}\n\nCommand:\n```\n{}\n```\n",
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            rsync,
+            command
+        );
+        if let Some(code) = self.exit_code {
+            report.push_str(&format!(
+                "\nExit code: {} ({})\n",
+                code,
+                describe_exit_code(code)
+            ));
+        }
+        report.push_str(&format!(
+            "\nErrors:\n```\n{}\n```\n",
+            self.error_logs.to_string().trim_end()
+        ));
+
+        report
+    }
+
+    fn error_report_buttons(&self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .small_button("Copy error report")
+                .on_hover_text(
+                    "Copies the errors, the command and the rsync version for a bug report",
+                )
+                .clicked()
+            {
+                ui.ctx().copy_text(self.error_report());
+            }
+            if ui
+                .small_button("Report issue…")
+                .on_hover_text(
+                    "Copies the error report and opens the issue tracker to paste it into",
+                )
+                .clicked()
+            {
+                ui.ctx().copy_text(self.error_report());
+                ui.ctx().open_url(egui::OpenUrl::new_tab(ISSUES_URL));
+            }
+        });
+    }
+
+    /// Marks a widget whose option differs from the global defaults, with a
+    /// right-click menu to go back to the default.
+    fn override_marker(
+        &mut self,
+        ui: &egui::Ui,
+        response: &egui::Response,
+        key: &str,
+        overridden: &[String],
+    ) {
+        if !overridden.iter().any(|x| x == key) {
+            return;
+        }
+
+        let center = egui::pos2(response.rect.left() - 5.0, response.rect.center().y);
+        ui.painter()
+            .circle_filled(center, 2.5, ui.visuals().selection.bg_fill);
+        response.context_menu(|ui| {
+            if ui.button("Reset to default").clicked() {
+                self.profiles.defaults.reset(&mut self.options, key);
+                ui.close();
+            }
+        });
+    }
+
+    /// One line on how the verification pass went.
+    fn verify_result(&self) -> String {
+        let differ = self.verify_diffs.len();
+        match &self.spot_check {
+            _ if !self.verify_passed && differ == 0 => {
+                "Verification could not be completed".to_string()
+            }
+            Some(x) if self.verify_passed => {
+                format!("Spot-check passed — all {} sampled files match", x.files)
+            }
+            Some(x) => format!(
+                "Spot-check failed — {} of {} sampled files match",
+                x.files.saturating_sub(differ),
+                x.files
+            ),
+            None if self.verify_passed => "Verification passed — no differences".to_string(),
+            None => format!("Verification failed: {} paths differ", differ),
+        }
+    }
+
+    /// Sends `signal` to the running rsync and the processes it started, if
+    /// there is one. rsync leads its own process group for this.
+    fn signal_child(&self, signal: Signal) -> bool {
+        match self.child_pid {
+            Some(pid) if !self.is_finished => {
+                signal::killpg(Pid::from_raw(pid as i32), signal).is_ok()
+            }
+            _ => false,
+        }
+    }
+
+    /// Fill for the progress bars: grey while paused, red once the run
+    /// failed, orange while errors are being reported.
+    fn progress_color(&self) -> Option<egui::Color32> {
+        if self.paused {
+            Some(egui::Color32::GRAY)
+        } else if self.is_finished && self.exit_code.is_some_and(|x| x != 0) {
+            Some(egui::Color32::RED)
+        } else if !self.error_logs.is_empty() {
+            Some(egui::Color32::from_rgb(255, 165, 0))
+        } else {
+            None
+        }
+    }
+
+    /// Applies what rsync reported since the last frame.
+    fn drain_messages(&mut self, ctx: &egui::Context) {
+        if let Some(check) = &mut self.hash_check
+            && !check.is_done()
+        {
+            for line in check.poll() {
+                self.error_logs.push_str(&line);
+                self.error_logs.push('\n');
+            }
+            if check.is_done() {
+                let kind = if check.mismatches + check.failures == 0 {
+                    LogKind::Info
+                } else {
+                    LogKind::Error
+                };
+                self.logs.push(LogLine::new(kind, check.status()));
+            }
+        }
+
+        if let Some(rx) = &self.progress {
+            let messages = rx.try_iter().collect::<Vec<_>>();
+            for msg in messages {
+                match msg {
+                    StateMessage::Deleted(total_progress) => {
+                        // With --info=progress2 rsync's own percentage already includes deletions.
+                        if !self.overall_progress {
+                            self.current_progress.total_progress = total_progress;
+                        }
+                    }
+                    StateMessage::Progress(mut x) => {
+                        // With --info=progress2 the reported percentage already covers the whole transfer.
+                        if self.overall_progress && self.phase == Phase::Transfer {
+                            x.total_progress = x.progress;
+                        }
+                        if x.checked.is_some() || self.overall_progress {
+                            self.scan_pending = false;
+                        }
+                        if x.speed_bps > 0.0
+                            && self.phase == Phase::Transfer
+                            && let Some(run) = &mut self.run
+                        {
+                            run.bytes.native_progress(Instant::now());
+                            run.speed_sum += x.speed_bps;
+                            run.speed_samples += 1;
+                            run.max_speed_bps = run.max_speed_bps.max(x.speed_bps);
+                        }
+                        if self.phase == Phase::Transfer
+                            && let Some(run) = &mut self.run
+                        {
+                            run.bytes.progress(x.bytes_sent);
+                        }
+                        self.current_progress = x;
+                    }
+                    StateMessage::NextFile(x) => {
+                        if self.phase == Phase::Verify {
+                            self.verify_diffs.push(x.line);
+                            continue;
+                        }
+
+                        // A new file starts from zero, even if the last 100% line of the previous one was skipped.
+                        self.current_progress.progress = 0.0;
+                        if let Some(run) = &mut self.run {
+                            run.files_transferred += 1;
+
+                            // With --info=progress2 the byte count is already a running total.
+                            if !self.overall_progress
+                                && !run.options.is_dry_run()
+                                && let Some(bps) = run.bytes.next_file(Instant::now(), x.size)
+                            {
+                                self.current_progress.speed_bps = bps;
+                                self.current_progress.speed =
+                                    format!("{}/s", format_bytes(bps as u64));
+                                self.current_progress.progress = 1.0;
+                            }
+                        }
+                    }
+                    StateMessage::Itemized(x) => {
+                        if self.phase == Phase::Transfer {
+                            if x.action != ItemAction::Other {
+                                self.logs.push(LogLine::new(
+                                    LogKind::from_action(x.action),
+                                    x.path.clone(),
+                                ));
+                            }
+                            self.file_log.push(x);
+                        }
+                    }
+                    StateMessage::SourceRemoved(_) => self.removed_count += 1,
+                    StateMessage::FinishedWithStatus(code) => self.finish_run(ctx, code),
+                    StateMessage::Error(x) => {
+                        self.logs.push(LogLine::new(
+                            LogKind::from_severity(stderr_severity(&x.line)),
+                            x.line.clone(),
+                        ));
+                        self.error_logs.push_str(&x.line);
+                        self.error_logs.push('\n');
+                        if let Some(run) = &mut self.run {
+                            run.error_count += 1;
+                        }
+                    }
+                    StateMessage::Stats(x) => {
+                        if self.phase == Phase::Transfer {
+                            self.final_stats = parse_rsync_stats(&x);
+                        }
+                    }
+                    StateMessage::Raw(line) => {
+                        while self.raw_output.len() >= self.config.max_raw_lines.max(1) {
+                            self.raw_output.pop_front();
+                        }
+                        self.raw_output.push_back(line);
+                    }
+                }
+            }
+        }
+    }
+
+    fn settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings;
+        let mut changed = false;
+        let mut new_defaults = None;
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.heading("Appearance");
+                ui.horizontal(|ui| {
+                    ui.label("Theme:");
+                    for theme in Theme::ALL {
+                        changed |= ui.radio_value(&mut self.config.theme, theme, theme.label()).changed();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Interface scale:");
+                    let slider = ui.add(egui::Slider::new(&mut self.config.ui_scale, 0.75..=2.0).step_by(0.05));
+                    // Saved once the drag ends rather than on every step.
+                    changed |= slider.drag_stopped() || slider.changed() && !slider.dragged();
+                });
+
+                ui.separator();
+                ui.heading("Logs");
+                ui.horizontal(|ui| {
+                    ui.label("Keep the last");
+                    changed |= ui
+                        .add(DragValue::new(&mut self.config.max_raw_lines).range(100..=100_000).speed(100.0))
+                        .changed();
+                    ui.label("lines of raw output on screen");
+                });
+                changed |= ui
+                    .checkbox(&mut self.config.write_raw_logs, "Write raw output to log directory")
+                    .changed();
+
+                ui.separator();
+                ui.heading("Notifications");
+                ui.horizontal(|ui| {
+                    changed |= ui
+                        .checkbox(&mut self.config.completion_cue, "Sound and attention request when a run ends")
+                        .changed();
+                    ui.add_enabled_ui(self.config.completion_cue, |ui| {
+                        ui.label("if it took at least");
+                        changed |= ui
+                            .add(DragValue::new(&mut self.config.completion_cue_min_secs).range(0..=3600).suffix(" s"))
+                            .changed();
+                    });
+                });
+
+                ui.separator();
+                ui.heading("Profile defaults");
+                ui.label("New profiles start from these, and follow them wherever they don't set a value of their own.");
+                let customized = GlobalDefaults::default().overridden(&self.profiles.defaults.options);
+                if customized.is_empty() {
+                    ui.weak("Same as the built-in defaults");
+                } else {
+                    let names: Vec<_> = customized.iter().map(|x| x.replace('_', " ")).collect();
+                    ui.label(format!("Changed from the built-in defaults: {}", names.join(", ")));
+                }
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Use the current form")
+                        .on_hover_text("Everything in the form except the source and destination")
+                        .clicked()
+                    {
+                        new_defaults = Some(GlobalDefaults::from_options(&self.options));
+                    }
+                    if ui.add_enabled(!customized.is_empty(), egui::Button::new("Reset to built-in")).clicked() {
+                        new_defaults = Some(GlobalDefaults::default());
+                    }
+                });
+            });
+        self.show_settings = open;
+
+        if changed {
+            self.save_config();
+        }
+        if let Some(defaults) = new_defaults
+            && let Err(e) = self.profiles.set_defaults(defaults)
+        {
+            self.error_logs.push_str(&format!("{:#}\n", e));
+        }
+    }
+
+    /// Runs the environment checks on a background thread.
+    fn refresh_environment(&mut self, ctx: &egui::Context) {
+        self.env_checked = true;
+        let (tx, rx) = mpsc::channel();
+        let binary = self.options.rsync_binary().to_string();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(environment::run_checks(&binary));
+            ctx.request_repaint();
+        });
+        self.env_check_rx = Some(rx);
+    }
+
+    fn environment_ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        if let Some(rx) = &self.env_check_rx
+            && let Ok(checks) = rx.try_recv()
+        {
+            self.env_checks = checks;
+            self.env_check_rx = None;
+        }
+
+        let problems = self
+            .env_checks
+            .iter()
+            .filter(|x| x.status != CheckStatus::Ok)
+            .count();
+        let icons: String = self.env_checks.iter().map(|x| x.status.icon()).collect();
+        let title = if self.env_check_rx.is_some() {
+            "Environment: checking…".to_string()
+        } else if problems == 0 {
+            format!("Environment {}", icons)
+        } else {
+            format!("Environment {} ({} to look at)", icons, problems)
+        };
+
+        egui::CollapsingHeader::new(title)
+            .id_salt("environment")
+            .show(ui, |ui| {
+                for check in &self.env_checks {
+                    let color = match check.status {
+                        CheckStatus::Ok => egui::Color32::GREEN,
+                        CheckStatus::Warning => egui::Color32::from_rgb(255, 165, 0),
+                        CheckStatus::Failed => egui::Color32::RED,
+                    };
+                    ui.horizontal(|ui| {
+                        ui.colored_label(color, check.status.icon());
+                        ui.label(egui::RichText::new(check.name).strong());
+                        ui.label(&check.detail);
+                    });
+                    if let Some(hint) = check.hint {
+                        ui.label(egui::RichText::new(hint).weak());
+                    }
+                }
+                if ui
+                    .add_enabled(self.env_check_rx.is_none(), egui::Button::new("Refresh"))
+                    .clicked()
+                {
+                    self.refresh_environment(ctx);
+                }
+            });
+    }
+
+    /// Shows the progress of a running transfer in the title bar, for when
+    /// the window is minimized or behind others.
+    fn update_window_title(&mut self, ctx: &egui::Context) {
+        let title = if self.progress.is_some() && !self.is_finished {
+            let mut title = format!(
+                "r-synced — {:.0}%",
+                self.current_progress.total_progress * 100.0
+            );
+            if !self.current_progress.speed.is_empty() {
+                title.push_str(&format!(" • {}", self.current_progress.speed));
+            }
+            title
+        } else {
+            "r-synced".to_string()
+        };
+
+        if title != self.window_title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.window_title = title;
+        }
+    }
+
+    /// Keeps `config.window` in sync with the viewport and saves it on close.
+    /// The restored geometry is only checked against the monitor on the first
+    /// frame, since the monitor size isn't known before the window exists.
+    fn track_window(&mut self, ctx: &egui::Context) {
+        let (outer, inner, monitor, close_requested) = ctx.input(|i| {
+            let viewport = i.viewport();
+            (
+                viewport.outer_rect,
+                viewport.inner_rect,
+                viewport.monitor_size,
+                viewport.close_requested(),
+            )
+        });
+        let (Some(outer), Some(inner)) = (outer, inner) else {
+            return;
+        };
+
+        // Viewport info is in zoomed ui points, the builder in main() wants logical ones.
+        let zoom = ctx.zoom_factor();
+        let window = WindowGeometry {
+            x: outer.min.x * zoom,
+            y: outer.min.y * zoom,
+            width: inner.width() * zoom,
+            height: inner.height() * zoom,
+        };
+
+        if !self.window_checked
+            && let Some(monitor) = monitor
+        {
+            self.window_checked = true;
+            // Without winit's list, all that's known is the current monitor's size.
+            let fallback = [egui::Rect::from_min_size(egui::Pos2::ZERO, monitor * zoom)];
+            let monitors = if self.monitors.is_empty() {
+                &fallback[..]
+            } else {
+                &self.monitors[..]
+            };
+            let clamped = window.clamped(monitors);
+            if clamped != window {
+                ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(
+                    egui::pos2(clamped.x, clamped.y) / zoom,
+                ));
+                ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(
+                    egui::vec2(clamped.width, clamped.height) / zoom,
+                ));
+            }
+        }
+
+        self.config.window = Some(window);
+        if close_requested {
+            self.save_config();
+        }
+    }
+
+    /// Include/exclude rules, reordered by dragging the handle on each row.
+    fn filter_rules_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Checked from top to bottom, the first matching rule wins:");
+
+        let rules = &mut self.options.filter_rules;
+        let count = rules.len();
+        let mut rows = Vec::with_capacity(count);
+        let mut moved = None;
+        let mut duplicated = None;
+        let mut removed = None;
+        for (i, rule) in rules.iter_mut().enumerate() {
+            let row = ui.horizontal(|ui| {
+                let handle = ui
+                    .add(egui::Label::new("☰").sense(egui::Sense::drag()))
+                    .on_hover_cursor(egui::CursorIcon::Grab);
+                if handle.drag_started() {
+                    self.drag_index = Some(i);
+                }
+
+                egui::ComboBox::from_id_salt(("filter_kind", i))
+                    .selected_text(rule.kind.label())
+                    .width(80.0)
+                    .show_ui(ui, |ui| {
+                        for kind in FilterKind::ALL {
+                            ui.selectable_value(&mut rule.kind, kind, kind.label());
+                        }
+                    });
+                let pattern = ui
+                    .add(
+                        egui::TextEdit::singleline(&mut rule.pattern)
+                            .id(egui::Id::new(("filter_pattern", i)))
+                            .hint_text("*.tmp"),
+                    )
+                    .on_hover_text("Alt+↑ / Alt+↓ to move");
+                if pattern.has_focus() {
+                    if i > 0
+                        && ui.input_mut(|x| x.consume_key(egui::Modifiers::ALT, egui::Key::ArrowUp))
+                    {
+                        moved = Some((i, i - 1));
+                    } else if i + 1 < count
+                        && ui.input_mut(|x| {
+                            x.consume_key(egui::Modifiers::ALT, egui::Key::ArrowDown)
+                        })
+                    {
+                        moved = Some((i, i + 1));
+                    }
+                }
+                if ui
+                    .add_enabled(i > 0, egui::Button::new("⏶").small())
+                    .on_hover_text("Move up")
+                    .clicked()
+                {
+                    moved = Some((i, i - 1));
+                }
+                if ui
+                    .add_enabled(i + 1 < count, egui::Button::new("⏷").small())
+                    .on_hover_text("Move down")
+                    .clicked()
+                {
+                    moved = Some((i, i + 1));
+                }
+                if ui.small_button("⧉").on_hover_text("Duplicate").clicked() {
+                    duplicated = Some(i);
+                }
+                if ui.button("✖").clicked() {
+                    removed = Some(i);
+                }
+            });
+            rows.push(row.response.rect);
+        }
+
+        if let Some(from) = self.drag_index {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+
+            if let Some(pointer) = ui.ctx().pointer_interact_pos()
+                && let (Some(first), Some(last)) = (rows.first(), rows.last())
+            {
+                let to = rows
+                    .iter()
+                    .position(|x| pointer.y < x.center().y)
+                    .unwrap_or(rows.len());
+                let gap = ui.spacing().item_spacing.y / 2.0;
+                let y = rows.get(to).map_or(last.bottom() + gap, |x| x.top() - gap);
+                ui.painter().hline(
+                    first.x_range(),
+                    y,
+                    egui::Stroke::new(2.0, ui.visuals().selection.bg_fill),
+                );
+                self.hover_index = Some(to);
+            }
+
+            if ui.input(|i| i.pointer.any_released()) {
+                if let Some(to) = self.hover_index {
+                    move_item(rules, from, to);
+                }
+                self.drag_index = None;
+                self.hover_index = None;
+            }
+        }
+
+        if let Some((from, to)) = moved {
+            rules.swap(from, to);
+            // Keep editing the rule that moved.
+            ui.memory_mut(|x| x.request_focus(egui::Id::new(("filter_pattern", to))));
+        }
+        if let Some(i) = duplicated {
+            rules.insert(i + 1, rules[i].clone());
+        }
+        if let Some(i) = removed {
+            rules.remove(i);
+            self.drag_index = None;
+            self.hover_index = None;
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Add").clicked() {
+                rules.push(FilterRule::default());
+            }
+            if ui
+                .add_enabled(!rules.is_empty(), egui::Button::new("Clear all"))
+                .clicked()
+            {
+                rules.clear();
+                self.drag_index = None;
+                self.hover_index = None;
+            }
+        });
+    }
+
+    fn write_script(&self, path: &Path) -> anyhow::Result<()> {
+        let mut lists = Vec::new();
+        if !self.options.exclude_from_content.trim().is_empty() {
+            lists.push(ScriptList {
+                placeholder: "@@exclude_from@@",
+                name: "exclude_from",
+                content: self.options.exclude_from_content.clone(),
+            });
+        }
+        if self.options.files_from && self.options.files_from_file.trim().is_empty() {
+            lists.push(ScriptList {
+                placeholder: "@@files_from@@",
+                name: "files_from",
+                content: file_list_entries(&self.options).join("\n"),
+            });
+        }
+
+        let placeholder = |name: &str| {
+            lists
+                .iter()
+                .find(|x| x.name == name)
+                .map(|x| Path::new(x.placeholder))
+        };
+        let paths = ListPaths {
+            exclude_from: placeholder("exclude_from"),
+            files_from: placeholder("files_from").or_else(|| {
+                self.options
+                    .files_from
+                    .then(|| Path::new(self.options.files_from_file.trim()))
+            }),
+        };
+        let command = create_rsync_command(&self.options, paths, self.rsync_version.as_ref());
+
+        fs::write(path, script::render(&command, &self.options, &lists))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to make {} executable", path.display()))?;
+
+        Ok(())
+    }
+
+    fn save_profiles(&mut self) {
+        if let Err(e) = self.profiles.save() {
+            self.error_logs.push_str(&format!("{:#}\n", e));
+        }
+    }
+
+    /// Loads a profile into the form and starts it. The schedule counts from
+    /// now whether or not the run gets going, so a failing profile isn't
+    /// retried every frame.
+    fn run_profile(&mut self, ctx: &egui::Context, name: &str, automatic: bool) {
+        let Some(profile) = self.profiles.get_mut(name) else {
+            return;
+        };
+        profile.schedule_anchor = Some(Local::now());
+        profile.skip_next = false;
+        self.select_profile(name);
+        self.save_profiles();
+
+        self.run_profile = Some((name.to_string(), automatic));
+        self.request_run(ctx, automatic);
+    }
+
+    /// Starts a run of the form with the Run button's checks: never with
+    /// conflicting options, and moves only once confirmed. Nobody is there to
+    /// confirm an automatic run, so a move is refused instead.
+    fn request_run(&mut self, ctx: &egui::Context, automatic: bool) {
+        let refusal = if blocks_run(&check_conflicts(&self.options)) {
+            Some("Not run: resolve the conflicting options first.")
+        } else if self.options.move_files && !self.options.is_dry_run() && automatic {
+            Some("Not run: moving files has to be confirmed, so scheduled runs don't do it.")
+        } else {
+            None
+        };
+        if let Some(refusal) = refusal {
+            self.run_profile = None;
+            self.error_logs.clear();
+            self.error_logs.push_str(&format!("{}\n", refusal));
+            return;
+        }
+
+        self.ssh_attempt = 0;
+        if self.options.move_files && !self.options.is_dry_run() {
+            self.confirm_move = true;
+        } else {
+            self.start_run(ctx);
+        }
+    }
+
+    /// Starts the first scheduled profile that is due, unless something is already running.
+    fn run_scheduled(&mut self, ctx: &egui::Context) {
+        if !self.profiles.profiles.iter().any(|x| x.schedule.is_some()) {
+            return;
+        }
+        ctx.request_repaint_after(Duration::from_secs(1));
+
+        if self.progress.is_some()
+            || self.planned.is_some()
+            || self.retry_at.is_some()
+            || self.confirm_move
+        {
+            return;
+        }
+
+        let now = Local::now();
+        if let Some(batch) = &mut self.scheduled_batch
+            && batch.stopped
+        {
+            let mut skipped = false;
+            for profile in self
+                .profiles
+                .profiles
+                .iter_mut()
+                .filter(|x| x.next_run().is_some_and(|x| x <= now))
+            {
+                profile.schedule_anchor = Some(now);
+                batch.skipped.push(profile.name.clone());
+                skipped = true;
+            }
+            if skipped {
+                self.save_profiles();
+            }
+        }
+
+        let Some(profile) = self
+            .profiles
+            .profiles
+            .iter_mut()
+            .find(|x| x.next_run().is_some_and(|x| x <= now))
+        else {
+            if let Some(batch) = self.scheduled_batch.take() {
+                self.batch_summary = Some(batch.summary());
+            }
+            return;
+        };
+
+        if profile.skip_next {
+            profile.skip_next = false;
+            profile.schedule_anchor = Some(now);
+            self.save_profiles();
+        } else {
+            let name = profile.name.clone();
+            self.scheduled_batch.get_or_insert_default();
+            self.run_profile(ctx, &name, true);
+            // Runs that end before rsync starts, e.g. on a missing source, never reach the history.
+            if self.progress.is_none()
+                && self.planned.is_none()
+                && self.retry_at.is_none()
+                && let Some(batch) = &mut self.scheduled_batch
+            {
+                batch.record(
+                    &name,
+                    self.error_logs.is_empty(),
+                    self.config.schedule_stop_on_failure,
+                );
+            }
+        }
+    }
+
+    /// Loads a profile into the form. What was typed before any profile was
+    /// selected is kept aside and comes back when the selection is cleared.
+    fn select_profile(&mut self, name: &str) {
+        let Some(profile) = self.profiles.get_mut(name) else {
+            return;
+        };
+        if self.selected_profile.is_none() {
+            self.unsaved_options = Some(self.options.clone());
+        }
+        self.options = profile.options.clone();
+        self.selected_profile = Some(name.to_string());
+    }
+
+    fn deselect_profile(&mut self) {
+        self.selected_profile = None;
+        if let Some(options) = self.unsaved_options.take() {
+            self.options = options;
+        }
+    }
+
+    /// Sidebar listing the saved profiles, with rename on double-click,
+    /// duplicate and delete.
+    fn profiles_panel_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Profiles");
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.profile_name)
+                    .hint_text("New profile name")
+                    .desired_width(140.0),
+            );
+            let name = self.profile_name.trim().to_string();
+            let exists = self.profiles.contains(&name);
+            if ui
+                .add_enabled(
+                    !name.is_empty() && !exists,
+                    egui::Button::new("Save as new"),
+                )
+                .on_disabled_hover_text(if exists {
+                    "A profile with this name already exists"
+                } else {
+                    "Enter a name"
+                })
+                .clicked()
+            {
+                self.profiles.upsert(&name, self.options.clone());
+                self.save_profiles();
+                self.profile_name.clear();
+                self.select_profile(&name);
+            }
+        });
+        if let Some(name) = self.selected_profile.clone()
+            && ui.button(format!("Save changes to \"{}\"", name)).clicked()
+        {
+            self.profiles.upsert(&name, self.options.clone());
+            self.save_profiles();
+        }
+        ui.separator();
+
+        if self.profiles.profiles.is_empty() {
+            ui.label("No saved profiles yet.");
+            return;
+        }
+
+        let mut select = None;
+        let mut duplicate = None;
+        let mut rename = None;
+        egui::ScrollArea::vertical()
+            .id_salt("profile_list")
+            .show(ui, |ui| {
+                for profile in &self.profiles.profiles {
+                    ui.horizontal(|ui| {
+                        if let Some((from, to)) = &mut self.renaming_profile
+                            && *from == profile.name
+                        {
+                            let response =
+                                ui.add(egui::TextEdit::singleline(to).desired_width(140.0));
+                            response.request_focus();
+                            if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                                self.renaming_profile = None;
+                            } else if response.lost_focus() {
+                                rename = self.renaming_profile.take();
+                            }
+                            return;
+                        }
+
+                        let selected =
+                            self.selected_profile.as_deref() == Some(profile.name.as_str());
+                        let name = if profile.options.dry_run_only {
+                            format!("🔒 {}", profile.name)
+                        } else {
+                            profile.name.clone()
+                        };
+                        let label = ui
+                            .selectable_label(selected, name)
+                            .on_hover_text("Double-click to rename");
+                        if label.double_clicked() {
+                            self.renaming_profile =
+                                Some((profile.name.clone(), profile.name.clone()));
+                        } else if label.clicked() {
+                            select = Some((profile.name.clone(), selected));
+                        }
+
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.small_button("🗑").on_hover_text("Delete").clicked() {
+                                self.delete_profile = Some(profile.name.clone());
+                            }
+                            if ui.small_button("⧉").on_hover_text("Duplicate").clicked() {
+                                duplicate = Some(profile.name.clone());
+                            }
+                        });
+                    });
+                }
+            });
+
+        if let Some((name, selected)) = select {
+            if selected {
+                self.deselect_profile();
+            } else {
+                self.select_profile(&name);
+            }
+        }
+        if let Some(name) = duplicate
+            && self.profiles.duplicate(&name).is_some()
+        {
+            self.save_profiles();
+        }
+        if let Some((from, to)) = rename {
+            let to = to.trim().to_string();
+            if to.is_empty() || !self.profiles.rename(&from, &to) {
+                self.error_logs.push_str(&format!(
+                    "Cannot rename the profile \"{}\" to \"{}\".\n",
+                    from, to
+                ));
+            } else {
+                if self.selected_profile.as_deref() == Some(from.as_str()) {
+                    self.selected_profile = Some(to);
+                }
+                self.save_profiles();
+            }
+        }
+    }
+
+    /// Schedules of the saved profiles, with their next and last runs.
+    fn schedules_ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        if self.profiles.profiles.is_empty() {
+            ui.label("Save a profile to schedule it.");
+            return;
+        }
+
+        if ui
+            .checkbox(&mut self.config.schedule_stop_on_failure, "Stop on first failure")
+            .on_hover_text("When several runs are due at once and one fails, skip the others until their next turn")
+            .changed()
+        {
+            self.save_config();
+        }
+        if let Some(summary) = &self.batch_summary {
+            let mut dismissed = false;
+            ui.horizontal(|ui| {
+                ui.label(summary);
+                dismissed = ui.small_button("✖").on_hover_text("Dismiss").clicked();
+            });
+            if dismissed {
+                self.batch_summary = None;
+            }
+        }
+
+        let mut run = None;
+        let mut load = None;
+        let mut changed = false;
+        let now = Local::now();
+        for profile in &mut self.profiles.profiles {
+            ui.group(|ui| {
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(&profile.name).strong());
+                    if let Some(schedule) = &profile.schedule {
+                        ui.label(egui::RichText::new(schedule.label()).weak());
+                    }
+                    if ui.button("Load").clicked() {
+                        load = Some(profile.name.clone());
+                    }
+                    if ui.button("Run now").clicked() {
+                        run = Some(profile.name.clone());
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Schedule:");
+                    let before = profile.schedule;
+                    egui::ComboBox::from_id_salt(("profile_schedule", &profile.name))
+                        .selected_text(match profile.schedule {
+                            None => "None",
+                            Some(Schedule::Interval { .. }) => "Interval",
+                            Some(Schedule::Daily { .. }) => "Daily",
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut profile.schedule, None, "None");
+                            if ui
+                                .selectable_label(
+                                    matches!(profile.schedule, Some(Schedule::Interval { .. })),
+                                    "Interval",
+                                )
+                                .clicked()
+                            {
+                                profile.schedule = Some(Schedule::Interval { minutes: 60 });
+                            }
+                            if ui
+                                .selectable_label(
+                                    matches!(profile.schedule, Some(Schedule::Daily { .. })),
+                                    "Daily",
+                                )
+                                .clicked()
+                            {
+                                profile.schedule = Some(Schedule::Daily { hour: 3, minute: 0 });
+                            }
+                        });
+                    match &mut profile.schedule {
+                        Some(Schedule::Interval { minutes }) => {
+                            ui.add(
+                                DragValue::new(minutes)
+                                    .range(1..=10080)
+                                    .prefix("every ")
+                                    .suffix(" min"),
+                            );
+                        }
+                        Some(Schedule::Daily { hour, minute }) => {
+                            ui.add(DragValue::new(hour).range(0..=23).prefix("at "));
+                            ui.label(":");
+                            ui.add(
+                                DragValue::new(minute)
+                                    .range(0..=59)
+                                    .custom_formatter(|x, _| format!("{:02}", x)),
+                            );
+                        }
+                        None => {}
+                    }
+                    if profile.schedule != before {
+                        profile.schedule_anchor = Some(now);
+                        changed = true;
+                    }
+                });
+
+                if let Some(next) = profile.next_run() {
+                    ui.horizontal(|ui| {
+                        let remaining = (next - now).num_seconds().max(0) as u64;
+                        ui.label(format!(
+                            "Next run: {} (in {}){}",
+                            next.format("%Y-%m-%d %H:%M"),
+                            format_duration(remaining),
+                            if profile.skip_next { ", skipped" } else { "" }
+                        ));
+                        if ui
+                            .toggle_value(&mut profile.skip_next, "Skip next")
+                            .changed()
+                        {
+                            changed = true;
+                        }
+                    });
+                }
+
+                if let Some(last) = &profile.last_run {
+                    let text = format!(
+                        "Last {} run {}: exit code {}",
+                        if last.automatic {
+                            "automatic"
+                        } else {
+                            "manual"
+                        },
+                        last.started_at.format("%Y-%m-%d %H:%M"),
+                        last.exit_code
+                            .map(|x| x.to_string())
+                            .unwrap_or_else(|| "N/A".to_string())
+                    );
+                    let color = if last.exit_code == Some(0) {
+                        egui::Color32::GREEN
+                    } else {
+                        egui::Color32::RED
+                    };
+                    ui.colored_label(color, text);
+                }
+            });
+        }
+
+        if changed {
+            self.save_profiles();
+        }
+        if let Some(name) = load {
+            self.select_profile(&name);
+        }
+        if let Some(name) = run {
+            self.run_profile(ctx, &name, false);
+        }
+    }
+
+    fn apply_preset(&mut self, index: usize) {
+        let preset = &PRESETS[index];
+        let previous = self
+            .history
+            .previous_snapshot(&self.options.src, &self.options.dest);
+        preset.apply(&mut self.options, previous.as_deref());
+        self.form_notice = (preset.link_previous && previous.is_none()).then(|| {
+            "No earlier backup of this source found; --link-dest is left as it was".to_string()
+        });
+    }
+
+    fn save_config(&mut self) {
+        if let Err(e) = self.config.save() {
+            self.error_logs.push_str(&format!("{:#}\n", e));
+        }
+    }
+
+    fn record_history(&mut self) {
+        let Some(run) = self.run.take() else {
+            return;
+        };
+
+        if let Some((name, automatic)) = &run.profile
+            && let Some(profile) = self.profiles.get_mut(name)
+        {
+            profile.last_run = Some(LastRun {
+                started_at: run.started_at,
+                exit_code: run.exit_code,
+                automatic: *automatic,
+            });
+            self.save_profiles();
+        }
+
+        if let Some((name, true)) = &run.profile
+            && let Some(batch) = &mut self.scheduled_batch
+        {
+            let succeeded =
+                run.exit_code == Some(0) && run.verified != Some(false) && !self.cancelled;
+            batch.record(name, succeeded, self.config.schedule_stop_on_failure);
+        }
+
+        let duration_secs = run.started.elapsed().as_secs_f64();
+        self.history.push(HistoryEntry {
+            run_id: Some(run.id),
+            started_at: run.started_at,
+            duration_secs,
+            command: run.command,
+            options: run.options.without_secrets(),
+            stats: run.stats,
+            files_transferred: run.files_transferred,
+            exit_code: run.exit_code,
+            verified: run.verified,
+            raw_log: run.raw_log.map(|x| x.lock().unwrap().path().clone()),
+            bytes_transferred: run.bytes.total(),
+            avg_speed_bps: run.bytes.total() as f64 / duration_secs.max(1.0),
+            max_speed_bps: run.max_speed_bps,
+            error_count: run.error_count,
+            cancelled: self.cancelled,
+            logs: self
+                .logs
+                .iter()
+                .map(|x| x.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            error_logs: self.error_logs.to_string(),
+        });
+        self.selected_history = None;
+
+        if let Err(e) = self.history.save() {
+            self.error_logs.push_str(&format!("{:#}\n", e));
+        }
+    }
+
+    fn history_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("History");
+
+        if self.history.entries.is_empty() {
+            ui.label("No completed jobs yet.");
+            return;
+        }
+
+        egui::ScrollArea::vertical()
+            .id_salt("history_list")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for (i, entry) in self.history.entries.iter().enumerate() {
+                    let mut text = format!(
+                        "{} {} | {} → {}",
+                        if entry.cancelled {
+                            "⏹"
+                        } else if entry.succeeded() {
+                            "✔"
+                        } else {
+                            "✖"
+                        },
+                        entry.started_at.format("%Y-%m-%d %H:%M"),
+                        entry.options.src,
+                        entry.options.dest
+                    );
+                    if let Some(id) = &entry.run_id {
+                        text.push_str(&format!(" [#{}]", id));
+                    }
+                    if ui
+                        .selectable_label(self.selected_history == Some(i), text)
+                        .clicked()
+                    {
+                        self.selected_history = Some(i);
+                    }
+                }
+            });
+
+        let Some(entry) = self
+            .selected_history
+            .and_then(|i| self.history.entries.get(i))
+        else {
+            return;
+        };
+
+        ui.separator();
+        ui.label(format!("Command: {}", entry.command));
+        ui.label(format!(
+            "Exit code: {} | Duration: {} | Files: {}",
+            entry
+                .exit_code
+                .map(|x| x.to_string())
+                .unwrap_or_else(|| "N/A".to_string()),
+            format_duration(entry.duration_secs as u64),
+            entry.files_transferred
+        ));
+        if let Some(raw_log) = &entry.raw_log {
+            ui.label(format!("Raw output: {}", raw_log.display()));
+        }
+
+        if !entry.stats.is_empty() {
+            ui.collapsing("Stats", |ui| {
+                let mut stats = entry.stats.iter().collect::<Vec<_>>();
+                stats.sort();
+                for (key, value) in stats {
+                    ui.label(format!("{}: {}", key, value));
+                }
+            });
+        }
+
+        ui.group(|ui| {
+            ui.label("Logs");
+            ui.add_space(1f32);
+            egui::ScrollArea::vertical()
+                .id_salt("history_logs_scrollarea")
+                .auto_shrink([false; 2])
+                .max_height(150.0)
+                .show(ui, |ui| {
+                    ui.label(&entry.logs);
+                });
+        });
+
+        if !entry.error_logs.is_empty() {
+            ui.group(|ui| {
+                ui.label("Errors");
+                ui.add_space(1f32);
+                egui::ScrollArea::vertical()
+                    .id_salt("history_errors_scrollarea")
+                    .auto_shrink([false; 2])
+                    .max_height(100.0)
+                    .show(ui, |ui| {
+                        ui.label(&entry.error_logs);
+                    });
+            });
+        }
+
+        if ui.button("Re-run").clicked() {
+            self.options = entry.options.clone();
+            self.show_history = false;
+        }
+    }
+}
+
+impl eframe::App for AppState {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.drain_messages(ctx);
+        self.update_source_estimate(ctx);
+
+        ctx.set_theme(self.config.theme.preference());
+        ctx.set_pixels_per_point(self.config.ui_scale);
+        self.track_window(ctx);
+        self.refresh_rsync_version(ctx);
+        self.autosave_draft(ctx);
+        self.update_window_title(ctx);
+        if !self.env_checked {
+            self.refresh_environment(ctx);
+        }
+        self.run_scheduled(ctx);
+
+        if let Some(at) = self.retry_at {
+            if Instant::now() >= at {
+                self.retry_at = None;
+                self.start_run(ctx);
+            } else {
+                ctx.request_repaint_after(Duration::from_millis(250));
+            }
+        }
+
+        if self.show_settings {
+            self.settings_window(ctx);
+        }
+
+        if self.show_history && self.progress.is_none() {
+            egui::SidePanel::right("history_panel")
+                .resizable(true)
+                .show(ctx, |ui| self.history_ui(ui));
+        }
+
+        if self.show_profiles && self.progress.is_none() {
+            egui::SidePanel::left("profiles_panel")
+                .resizable(true)
+                .show(ctx, |ui| self.profiles_panel_ui(ui));
+        }
+
+        if let Some(name) = &self.delete_profile {
+            let mut decision = None;
+            egui::Window::new("Delete Profile")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label(format!(
+                        "Delete the profile \"{}\"? Its schedule is removed too.",
+                        name
+                    ));
+                    ui.horizontal(|ui| {
+                        if ui.button("Delete").clicked() {
+                            decision = Some(true);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            decision = Some(false);
+                        }
+                    });
+                });
+
+            if let Some(delete) = decision {
+                let name = self.delete_profile.take().unwrap();
+                if delete {
+                    self.profiles.remove(&name);
+                    self.save_profiles();
+                    if self.selected_profile.as_deref() == Some(name.as_str()) {
+                        self.deselect_profile();
+                    }
+                }
+            }
+        }
+
+        if self.confirm_move {
+            egui::Window::new("Confirm Move")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.colored_label(
+                        egui::Color32::RED,
+                        "Source files will be deleted after they have been copied to the destination.",
+                    );
+                    ui.label(format!("Source: {}", self.options.src));
+                    ui.horizontal(|ui| {
+                        if ui.button("Move Files").clicked() {
+                            self.confirm_move = false;
+                            self.ssh_attempt = 0;
+                            self.start_run(ctx);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.confirm_move = false;
+                            self.run_profile = None;
+                        }
+                    });
+                });
+        }
+
+        if let Some(index) = self.confirm_preset {
+            let preset = &PRESETS[index];
+            egui::Window::new(format!("{} mode", preset.name))
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label(preset.confirmation.unwrap_or_default());
+                    ui.horizontal(|ui| {
+                        if ui.button("Continue").clicked() {
+                            self.confirm_preset = None;
+                            self.config.confirmed_presets.push(preset.name.to_string());
+                            self.save_config();
+                            self.apply_preset(index);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.confirm_preset = None;
+                        }
+                    });
+                });
+        }
+
+        if self.confirm_cancel {
+            egui::Window::new("Stop this transfer?")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label(
+                        "rsync will be interrupted; files already copied stay in the destination.",
+                    );
+                    ui.horizontal(|ui| {
+                        if ui.button("Stop").clicked() {
+                            self.confirm_cancel = false;
+                            if self.signal_child(Signal::SIGINT) {
+                                // A stopped process only sees the interrupt once it runs again.
+                                if std::mem::take(&mut self.paused) {
+                                    self.signal_child(Signal::SIGCONT);
+                                }
+                                self.cancelled = true;
+                                self.logs
+                                    .push(LogLine::new(LogKind::Info, "Operation Cancelled"));
+                                self.notices.push("Operation Cancelled".to_string());
+                            }
+                        }
+                        if ui.button("Keep running").clicked() {
+                            self.confirm_cancel = false;
+                        }
+                    });
+                });
+        }
+
+        if self.restore_draft.is_some() {
+            egui::Window::new("Restore last session?")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label(
+                        "r-synced didn't exit cleanly last time. Restore the settings it had then?",
+                    );
+                    ui.horizontal(|ui| {
+                        if ui.button("Restore").clicked() {
+                            self.options = self.restore_draft.take().unwrap();
+                        }
+                        if ui.button("Discard").clicked() {
+                            self.restore_draft = None;
+                            draft::clear();
+                        }
+                    });
+                });
+        }
+
+        if let Some(path) = &mut self.export_script {
+            let mut decision = None;
+            egui::Window::new("Export as Script")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label("Writes a bash script that runs this transfer without r-synced.");
+                    ui.horizontal(|ui| {
+                        ui.label("Save to:");
+                        ui.add(egui::TextEdit::singleline(path).desired_width(300.0));
+                    });
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(!path.trim().is_empty(), egui::Button::new("Export"))
+                            .clicked()
+                        {
+                            decision = Some(true);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            decision = Some(false);
+                        }
+                    });
+                });
+
+            if let Some(export) = decision {
+                let path = PathBuf::from(self.export_script.take().unwrap().trim());
+                if export {
+                    match self.write_script(&path) {
+                        Ok(()) => {
+                            self.form_notice = Some(format!("Script written to {}", path.display()))
+                        }
+                        Err(e) => self.error_logs.push_str(&format!("{:#}\n", e)),
+                    }
+                }
+            }
+        }
+
+        if let Some((path, format)) = &mut self.export_stats {
+            let mut decision = None;
+            egui::Window::new("Export Stats")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label("Saves the transfer stats of this run with numbers as plain values.");
+                    ui.horizontal(|ui| {
+                        for option in [StatsFormat::Json, StatsFormat::Csv] {
+                            if ui.radio_value(format, option, option.label()).changed() {
+                                *path = Path::new(path.trim())
+                                    .with_extension(option.extension())
+                                    .to_string_lossy()
+                                    .to_string();
+                            }
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Save to:");
+                        ui.add(egui::TextEdit::singleline(path).desired_width(300.0));
+                    });
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(!path.trim().is_empty(), egui::Button::new("Export"))
+                            .clicked()
+                        {
+                            decision = Some(true);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            decision = Some(false);
+                        }
+                    });
+                });
+
+            if let Some(export) = decision {
+                let (path, format) = self.export_stats.take().unwrap();
+                let path = PathBuf::from(path.trim());
+                if export && let Some(entry) = self.history.entries.first() {
+                    match stats_export::export(&path, format, entry, &self.final_stats) {
+                        Ok(()) => self
+                            .notices
+                            .push(format!("Stats written to {}", path.display())),
+                        Err(e) => self.error_logs.push_str(&format!("{:#}\n", e)),
+                    }
+                }
+            }
+        }
+
+        if let Some(rx) = &self.compare_rx
+            && let Ok(result) = rx.try_recv()
+        {
+            if let Some(compare) = &mut self.compare {
+                compare.result = Some(result);
+            }
+            self.compare_rx = None;
+        }
+        if let Some(compare) = &self.compare {
+            let mut open = true;
+            egui::Window::new("Compare")
+                .open(&mut open)
+                .collapsible(false)
+                .default_size([450.0, 400.0])
+                .show(ctx, |ui| {
+                    ui.label(format!("{}  ⇄  {}", compare.src, compare.dest));
+                    ui.label(egui::RichText::new("Nothing is transferred.").weak());
+                    ui.separator();
+                    match &compare.result {
+                        None => {
+                            ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label("Comparing…");
+                            });
+                        }
+                        Some(Ok(comparison)) => comparison.ui(ui),
+                        Some(Err(e)) => {
+                            ui.colored_label(egui::Color32::RED, format!("Compare failed: {}", e));
+                        }
+                    }
+                });
+            // A compare still running is left to finish; its result is dropped.
+            if !open {
+                self.compare = None;
+                self.compare_rx = None;
+            }
+        }
+
+        if let Some(preview) = &self.diff_preview {
+            let mut open = true;
+            egui::Window::new("Preview Changes")
+                .open(&mut open)
+                .collapsible(false)
+                .default_size([450.0, 400.0])
+                .show(ctx, |ui| {
+                    ui.label(format!("{}  →  {}", self.options.src, self.options.dest));
+                    if let Some(summary) = &self.dry_run_summary {
+                        ui.label(egui::RichText::new(summary.text()).strong());
+                    }
+                    ui.separator();
+                    egui::ScrollArea::vertical().show(ui, |ui| preview.ui(ui));
+                });
+            if !open {
+                self.diff_preview = None;
+            }
+        }
+
+        if let Some(planned) = &mut self.planned {
+            let deleted = deleted_files(&planned.deletions);
+            let mass_deletion = planned.dest_files > 0
+                && deleted as f64 / planned.dest_files as f64 >= MASS_DELETION_FRACTION;
+            let mut decision = None;
+            egui::Window::new("Confirm Deletions")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    if mass_deletion {
+                        ui.heading(
+                            egui::RichText::new(format!(
+                                "⚠ This will delete {} files — are you sure?",
+                                format_count(deleted)
+                            ))
+                            .color(egui::Color32::RED)
+                            .strong(),
+                        );
+                        ui.label(format!(
+                            "That is about {:.0}% of the {} files in the destination. A missing or extra trailing slash on the source is a common cause.",
+                            (deleted as f64 / planned.dest_files as f64 * 100.0).min(100.0),
+                            format_count(planned.dest_files)
+                        ));
+                        ui.checkbox(&mut planned.mass_deletion_confirmed, "I want to delete these files");
+                        ui.separator();
+                    }
+                    if let Some(summary) = &self.dry_run_summary {
+                        ui.label(egui::RichText::new(summary.text()).heading());
+                    }
+                    transfer_estimate_ui(ui, self.transfer_estimate, &self.history);
+                    ui.colored_label(
+                        egui::Color32::RED,
+                        format!(
+                            "Will transfer {} files and delete {}:",
+                            format_count(planned.files_count),
+                            format_count(planned.deletions.len() as u64)
+                        ),
+                    );
+                    let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                    egui::ScrollArea::vertical()
+                        .id_salt("planned_deletions")
+                        .max_height(150.0)
+                        .show_rows(ui, row_height, planned.deletions.len(), |ui, range| {
+                            for path in &planned.deletions[range] {
+                                ui.label(path);
+                            }
+                        });
+                    ui.horizontal(|ui| {
+                        let allowed = !mass_deletion || planned.mass_deletion_confirmed;
+                        if ui.add_enabled(allowed, egui::Button::new("Run")).clicked() {
+                            decision = Some(true);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            decision = Some(false);
+                        }
+                    });
+                });
+
+            match decision {
+                Some(true) => {
+                    let planned = self.planned.take().unwrap();
+                    self.launch_run(ctx, planned);
+                }
+                Some(false) => self.planned = None,
+                None => {}
+            }
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("r-synced");
+                let today = self.config.usage.today();
+                if today > 0 {
+                    ui.weak(format!("Today: {} transferred", format_bytes(today)))
+                        .on_hover_text("Sent and received by the transfers that ran to the end since midnight; dry-runs and stopped runs don't count");
+                }
+                if self.progress.is_none() {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.toggle_value(&mut self.show_settings, "⚙").on_hover_text("Settings");
+                        ui.toggle_value(&mut self.show_history, "History");
+                        ui.toggle_value(&mut self.show_profiles, "Profiles");
+                    });
+                }
+            });
+            if self.progress.is_some() {
+                egui::Window::new(&self.progress_title)
+                    .id(egui::Id::new("operation_progress"))
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+                    .show(ctx, |ui| {
+                        ui.group(|ui| {
+                            if let Some(summary) = &self.dry_run_summary {
+                                ui.label(egui::RichText::new(summary.text()).strong());
+                            }
+                            if self.phase == Phase::Verify && !self.is_finished {
+                                ui.label("Verifying…");
+                            }
+
+                            let fill = self.progress_color();
+                            if self.scan_pending && !self.is_finished {
+                                ui.add(ProgressBar::new(0.0).animate(!self.paused).text("Scanning…"));
+                            } else {
+                                let mut progress_bar = ProgressBar::new(self.current_progress.total_progress)
+                                    .show_percentage()
+                                    .text(format!("{:.0}%", self.current_progress.total_progress * 100.0));
+                                if let Some(fill) = fill {
+                                    progress_bar = progress_bar.fill(fill);
+                                }
+                                ui.add(progress_bar);
+                            }
 
-                            let progress_bar = ProgressBar::new(self.current_progress.progress)
-                                .show_percentage()
-                                .text(format!("{:.0}%", self.current_progress.progress * 100.0));
-                            ui.add(progress_bar);
+                            if self.phase == Phase::Transfer {
+                                if !self.overall_progress {
+                                    let mut progress_bar = ProgressBar::new(self.current_progress.progress)
+                                        .show_percentage()
+                                        .text(format!("{:.0}%", self.current_progress.progress * 100.0));
+                                    if let Some(fill) = fill {
+                                        progress_bar = progress_bar.fill(fill);
+                                    }
+                                    ui.add(progress_bar);
+                                }
+                                if self.paused {
+                                    ui.colored_label(egui::Color32::GRAY, "Paused");
+                                }
 
-                            ui.label(format!("Speed: {} | Size: {} | ETA: {}", self.current_progress.speed, format_bytes(self.current_progress.bytes_sent), self.current_progress.time));
+                                ui.label(format!("Speed: {} | Size: {} | ETA: {}", self.current_progress.speed, format_bytes(self.current_progress.bytes_sent), self.current_progress.time));
+                                if let Some((checked, total)) = self.current_progress.checked {
+                                    ui.label(format!("Checking: {} of {}", format_count(checked), format_count(total)));
+                                }
+                                if let Some(run) = &self.run
+                                    && let Some(text) = run.overall_eta()
+                                {
+                                    ui.label(text);
+                                }
+                            } else if self.is_finished {
+                                let color = if self.verify_passed {
+                                    egui::Color32::GREEN
+                                } else {
+                                    egui::Color32::RED
+                                };
+                                ui.colored_label(color, self.verify_result());
+                            }
 
                             ui.group(|ui| {
-                                ui.label("Logs");
+                                ui.horizontal(|ui| {
+                                    ui.label("Logs");
+                                    ui.checkbox(&mut self.show_plain_logs, "Plain text");
+                                });
                                 ui.add_space(1f32);
-                                egui::ScrollArea::vertical()
-                                    .id_salt("logs_scrollarea")
+                                if self.show_plain_logs {
+                                    let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                                    egui::ScrollArea::vertical()
+                                        .id_salt("logs_scrollarea")
+                                        .stick_to_bottom(true)
+                                        .auto_shrink([false; 2])
+                                        .max_height(100.0)
+                                        .show_rows(ui, row_height, self.logs.len(), |ui, range| {
+                                            for line in &self.logs[range] {
+                                                let mut text = egui::RichText::new(&line.text);
+                                                if let Some(color) = line.kind.color(ui.visuals()) {
+                                                    text = text.color(color);
+                                                }
+                                                ui.label(text);
+                                            }
+                                        });
+                                } else {
+                                    self.file_log.ui(ui);
+                                    for notice in &self.notices {
+                                        ui.label(notice);
+                                    }
+                                }
+                            });
+
+                            ui.collapsing("Raw output", |ui| {
+                                let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                                egui::ScrollArea::both()
+                                    .id_salt("raw_output_scrollarea")
                                     .stick_to_bottom(true)
                                     .auto_shrink([false; 2])
                                     .max_height(100.0)
-                                    .show(ui, |ui| {
-                                        ui.label(&self.logs);
+                                    .show_rows(ui, row_height, self.raw_output.len(), |ui, range| {
+                                        for line in self.raw_output.range(range) {
+                                            ui.add(egui::Label::new(egui::RichText::new(line).monospace()).extend());
+                                        }
                                     });
                             });
 
-
                             if !self.error_logs.is_empty() {
                                 ui.group(|ui| {
                                     ui.label("Errors");
+                                    if let Some(summary) = self.error_logs.summary() {
+                                        ui.label(egui::RichText::new(summary).strong());
+                                    }
                                     ui.add_space(1f32);
                                     egui::ScrollArea::vertical()
                                         .id_salt("errors_scrollarea")
@@ -394,22 +4457,101 @@ impl eframe::App for AppState {
                                         .auto_shrink([false; 2])
                                         .max_height(100.0)
                                         .show(ui, |ui| {
-                                            ui.label(&self.error_logs);
+                                            ui.label(self.error_logs.to_string());
                                         });
+                                    self.error_report_buttons(ui);
                                 });
                             }
 
-                            if self.is_finished {
-                                if ui.button("Continue").clicked() {
-                                    self.progress = None
+                            if self.is_finished
+                                && let Some(summary) = &self.summary
+                            {
+                                ui.label(summary);
+                            }
+                            if let Some(check) = &self.hash_check {
+                                let color = if check.mismatches + check.failures > 0 {
+                                    egui::Color32::RED
+                                } else if check.is_done() {
+                                    egui::Color32::GREEN
+                                } else {
+                                    ui.visuals().text_color()
+                                };
+                                ui.colored_label(color, check.status());
+                            }
+
+                            if self.is_finished
+                                && let Some((speedup, sent)) = delta_efficiency(&self.final_stats)
+                            {
+                                ui.add(egui::ProgressBar::new(sent.min(1.0) as f32).text(format!(
+                                    "Delta efficiency: {:.2}x speedup (rsync sent {:.0}% of total data)",
+                                    speedup,
+                                    sent * 100.0
+                                )));
+                            }
+
+                            if self.is_finished && !self.final_stats.is_empty() {
+                                ui.collapsing("Transfer stats", |ui| {
+                                    let bytes = |key: &str| {
+                                        self.final_stats
+                                            .get(key)
+                                            .and_then(|x| parse_human_number(x.trim_end_matches(" bytes")))
+                                            .map(format_bytes)
+                                            .unwrap_or_else(|| "N/A".to_string())
+                                    };
+                                    ui.label(format!(
+                                        "Speedup: {}",
+                                        self.final_stats.get("Speedup").map(String::as_str).unwrap_or("N/A")
+                                    ));
+                                    ui.label(format!("Literal data: {}", bytes("Literal data")));
+                                    ui.label(format!("Matched data: {}", bytes("Matched data")));
+                                    ui.label(format!("File list size: {}", bytes("File list size")));
+                                });
+                            }
+
+                            if self.is_finished && self.cancelled {
+                                ui.colored_label(egui::Color32::from_rgb(255, 165, 0), "⏹ Cancelled by user");
+                            } else if self.is_finished
+                                && let Some(code) = self.exit_code
+                            {
+                                if code == 0 {
+                                    ui.colored_label(egui::Color32::GREEN, "Transfer complete (exit code: 0)");
+                                } else {
+                                    ui.colored_label(
+                                        egui::Color32::RED,
+                                        format!("Transfer failed (exit code: {}): {}", code, describe_exit_code(code)),
+                                    );
                                 }
+                            }
+
+                            if self.is_finished {
+                                ui.horizontal(|ui| {
+                                    if ui.button("Continue").clicked() {
+                                        self.progress = None
+                                    }
+                                    if !self.final_stats.is_empty() && ui.button("Export stats…").clicked() {
+                                        let path = dirs::home_dir().unwrap_or_default().join("r-synced-stats.csv");
+                                        self.export_stats = Some((path.to_string_lossy().to_string(), StatsFormat::Csv));
+                                    }
+                                    if split_remote(&self.options.dest).is_none()
+                                        && ui.button("Open destination").clicked()
+                                        && let Err(e) = open_in_file_manager(Path::new(&self.options.dest))
+                                    {
+                                        self.error_logs.push_str(&format!("{:#}\n", e));
+                                    }
+                                });
                             } else {
-                                if ui.button("Cancel").clicked() {
-                                    let pid = Pid::from_raw(self.child.as_ref().unwrap().id() as i32);
-                                    if signal::kill(pid, Signal::SIGINT).is_ok() {
-                                        self.logs.push_str("Operation Cancelled\n");
+                                ui.horizontal(|ui| {
+                                    let label = if self.paused { "Resume" } else { "Pause" };
+                                    if ui.add_enabled(!self.cancelled, egui::Button::new(label)).clicked() {
+                                        let signal = if self.paused { Signal::SIGCONT } else { Signal::SIGSTOP };
+                                        if self.signal_child(signal) {
+                                            self.paused = !self.paused;
+                                        }
                                     }
-                                }
+                                    if ui.add_enabled(!self.cancelled, egui::Button::new("Cancel")).clicked() {
+                                        self.confirm_cancel = true;
+                                    }
+                                });
                             }
                         });
                     });
@@ -417,37 +4559,419 @@ impl eframe::App for AppState {
                 egui::ScrollArea::vertical()
                     .auto_shrink([false; 2])
                     .show(ui, |ui| {
+                        self.environment_ui(ui, ctx);
+
+                        if self.config.read_only {
+                            ui.colored_label(
+                                egui::Color32::from_rgb(255, 165, 0),
+                                "⚠ Settings were saved by a newer version of r-synced. They are read-only; changes won't be kept.",
+                            );
+                        }
+                        if self.options.dry_run_only {
+                            ui.colored_label(
+                                egui::Color32::LIGHT_BLUE,
+                                "🔒 Dry-run only: runs preview the changes and never write to the destination.",
+                            );
+                        }
+
+                        let recent_count = self.config.recent_sources.len() + self.config.recent_dests.len();
+
                         ui.horizontal(|ui| {
                             ui.label("Source:");
-                            ui.text_edit_singleline(&mut self.src);
+                            let response = ui.text_edit_singleline(&mut self.options.src);
+                            if std::mem::take(&mut self.focus_source) {
+                                response.request_focus();
+                            }
+                            if recent_paths_menu(ui, &mut self.config.recent_sources, &mut self.options.src) {
+                                response.request_focus();
+                            }
                         });
 
+                        if is_directory_like(&self.options.src) {
+                            ui.horizontal(|ui| {
+                                let contents = self.options.src.trim_end().ends_with('/');
+                                if ui.selectable_label(!contents, "Copy folder").clicked() {
+                                    self.options.src = set_trailing_slash(self.options.src.trim_end(), false);
+                                }
+                                if ui.selectable_label(contents, "Copy contents").clicked() {
+                                    self.options.src = set_trailing_slash(self.options.src.trim_end(), true);
+                                }
+
+                                let src = self.options.src.trim();
+                                let name = endpoint_name(src);
+                                let dest = self.options.dest.trim().trim_end_matches('/');
+                                let dest = if dest.is_empty() { "the destination" } else { dest };
+                                if src.ends_with('/') {
+                                    ui.weak(format!("Files inside {} go straight into {}", src, dest));
+                                } else {
+                                    ui.weak(format!("Creates {}/{}", dest, name));
+                                }
+                            });
+                        }
+
                         ui.horizontal(|ui| {
                             ui.label("Destination:");
-                            ui.text_edit_singleline(&mut self.dest);
+                            let response = ui.text_edit_singleline(&mut self.options.dest).on_hover_ui(|ui| {
+                                match self.history.query_stats_for_destination(&self.options.dest) {
+                                    Some(stats) => {
+                                        ui.label(egui::RichText::new("Destination stats").strong());
+                                        ui.label(format!("Runs: {}", stats.runs));
+                                        ui.label(format!("Total transferred: {}", format_bytes(stats.total_bytes)));
+                                        ui.label(format!("Average speed: {}/s", format_bytes(stats.avg_speed_bps as u64)));
+                                        ui.label(format!("Success rate: {:.0}%", stats.success_rate * 100.0));
+                                    }
+                                    None => {
+                                        ui.label("No previous runs to this destination");
+                                    }
+                                }
+                            });
+                            if recent_paths_menu(ui, &mut self.config.recent_dests, &mut self.options.dest) {
+                                response.request_focus();
+                            }
                         });
 
-                        let command = create_rsync_command(self);
-                        ui.group(|ui| {
-                            ui.label("Command:");
-                            ui.label(format!("{:?}", command));
+                        if recent_count != self.config.recent_sources.len() + self.config.recent_dests.len() {
+                            self.save_config();
+                        }
+
+                        // Only rebuilt while expanded.
+                        egui::CollapsingHeader::new("Command")
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                command_preview(ui, &self.options, self.rsync_version.as_ref());
+                                if let Some(transport) = transport_text(&self.options) {
+                                    ui.label(transport);
+                                }
+                                if self.options.low_priority {
+                                    ui.label(format!("Priority: nice {}", self.options.nice_level));
+                                }
+                            });
+
+                        let conflicts = check_conflicts(&self.options);
+                        if !conflicts.is_empty() {
+                            egui::Frame::new()
+                                .fill(ui.visuals().extreme_bg_color)
+                                .stroke(egui::Stroke::new(
+                                    1.0,
+                                    if blocks_run(&conflicts) {
+                                        egui::Color32::RED
+                                    } else {
+                                        ui.visuals().warn_fg_color
+                                    },
+                                ))
+                                .inner_margin(6.0)
+                                .show(ui, |ui| {
+                                    for conflict in &conflicts {
+                                        ui.colored_label(conflict.color(ui.visuals()), format!("⚠ {}", conflict.message));
+                                    }
+                                });
+                        }
+
+                        let dest = self.options.dest.trim();
+                        if self.dest_filesystem.0 != dest {
+                            let filesystem = split_remote(dest)
+                                .is_none()
+                                .then(|| Filesystem::detect(Path::new(dest)))
+                                .flatten();
+                            self.dest_filesystem = (dest.to_string(), filesystem);
+                        }
+                        if let Some(filesystem) = self.dest_filesystem.1
+                            && !dest.is_empty()
+                        {
+                            let unsupported = filesystem.unsupported(&self.options);
+                            if !unsupported.is_empty() {
+                                egui::Frame::new()
+                                    .fill(ui.visuals().extreme_bg_color)
+                                    .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(255, 165, 0)))
+                                    .inner_margin(6.0)
+                                    .show(ui, |ui| {
+                                        ui.colored_label(
+                                            egui::Color32::from_rgb(255, 165, 0),
+                                            format!(
+                                                "⚠ The destination is on {}, which can't keep {}. rsync will report an error for every file it fails to set them on.",
+                                                filesystem.name(),
+                                                unsupported.join(", ")
+                                            ),
+                                        );
+                                        if ui
+                                            .button("Adjust options")
+                                            .on_hover_text("Turns those off, keeping -r and -t in place of -a")
+                                            .clicked()
+                                        {
+                                            filesystem.adjust(&mut self.options);
+                                        }
+                                    });
+                            }
+                        }
+
+                        ui.horizontal(|ui| {
+                            ui.label("Presets:");
+                            for (index, preset) in PRESETS.iter().enumerate() {
+                                if ui.button(preset.name).on_hover_text(preset.description).clicked() {
+                                    let confirmed = self.config.confirmed_presets.iter().any(|x| x == preset.name);
+                                    if preset.confirmation.is_some() && !confirmed {
+                                        self.confirm_preset = Some(index);
+                                    } else {
+                                        self.apply_preset(index);
+                                    }
+                                }
+                            }
                         });
 
-                        ui.checkbox(&mut self.archive, "Archive (-a)");
-                        ui.add_enabled(!self.archive, Checkbox::new(&mut self.recursive, "Recursive (-r)"));
-                        ui.add_enabled(!self.archive, Checkbox::new(&mut self.symlinks, "Symlinks (-l)"));
-                        ui.add_enabled(!self.archive, Checkbox::new(&mut self.permissions, "Save Permissions (-p)"));
-                        ui.add_enabled(!self.archive, Checkbox::new(&mut self.time, "Save Modification Time (-t)"));
-                        ui.add_enabled(!self.archive, Checkbox::new(&mut self.group, "Save Group (-g)"));
-                        ui.checkbox(&mut self.compress, "Compress (-z)");
-                        ui.checkbox(&mut self.checksum, "Checksum (-c)");
-                        ui.checkbox(&mut self.dry_run, "Dry Run (-n)");
+                        let overridden = self.profiles.defaults.overridden(&self.options);
+                        if !overridden.is_empty() {
+                            egui::CollapsingHeader::new(format!("Differs from the defaults ({})", overridden.len()))
+                                .id_salt("overridden_options")
+                                .show(ui, |ui| {
+                                    ui.weak("Everything else follows the profile defaults in Settings. Marked options can also be reset with a right-click.");
+                                    let mut reset = None;
+                                    for key in &overridden {
+                                        ui.horizontal(|ui| {
+                                            ui.label(key.replace('_', " "));
+                                            if ui.small_button("↺").on_hover_text("Reset to default").clicked() {
+                                                reset = Some(key.clone());
+                                            }
+                                        });
+                                    }
+                                    if let Some(key) = reset {
+                                        self.profiles.defaults.reset(&mut self.options, &key);
+                                    }
+                                });
+                        }
+
+                        let archive = self.options.archive;
+                        let response = flag_checkbox(ui, true, &mut self.options.archive, "Archive (-a)", help_text::ARCHIVE);
+                        self.override_marker(ui, &response, "archive", &overridden);
+                        let response = flag_checkbox(ui, !archive, &mut self.options.recursive, "Recursive (-r)", help_text::RECURSIVE);
+                        outline_conflict(ui, &response, "-r", &conflicts);
+                        self.override_marker(ui, &response, "recursive", &overridden);
+                        ui.label("Symlink handling:");
+                        ui.indent("symlink_handling", |ui| self.symlink_handling_ui(ui));
+                        flag_checkbox(
+                            ui,
+                            true,
+                            &mut self.options.keep_dirlinks,
+                            "Keep symlinked directories at destination (-K)",
+                            help_text::KEEP_DIRLINKS,
+                        );
+                        if self.options.keep_dirlinks {
+                            ui.label("Only matters where the destination has a symlink in place of a source directory; rsync then writes into the directory it points to instead of replacing the link.");
+                        }
+                        let response = flag_checkbox(ui, !archive, &mut self.options.permissions, "Save Permissions (-p)", help_text::PERMS);
+                        self.override_marker(ui, &response, "permissions", &overridden);
+                        let response = flag_checkbox(ui, !archive, &mut self.options.time, "Save Modification Time (-t)", help_text::TIMES);
+                        self.override_marker(ui, &response, "time", &overridden);
+                        let response = flag_checkbox(ui, !archive, &mut self.options.group, "Save Group (-g)", help_text::GROUP);
+                        self.override_marker(ui, &response, "group", &overridden);
+                        flag_checkbox(
+                            ui,
+                            flag_supported(self.rsync_version.as_ref(), "--atimes"),
+                            &mut self.options.atimes,
+                            "Save Access Time (--atimes)",
+                            help_text::ATIMES,
+                        )
+                        .on_disabled_hover_text(requires_text("--atimes"));
+                        flag_checkbox(
+                            ui,
+                            crtimes_supported(self.rsync_version.as_ref()),
+                            &mut self.options.crtimes,
+                            "Save Creation Time (--crtimes)",
+                            help_text::CRTIMES,
+                        )
+                        .on_disabled_hover_text(if cfg!(target_os = "macos") {
+                            requires_text("--crtimes")
+                        } else {
+                            "Only supported on macOS".to_string()
+                        });
+                        flag_checkbox(
+                            ui,
+                            flag_supported(self.rsync_version.as_ref(), "--open-noatime"),
+                            &mut self.options.open_noatime,
+                            "Don't change access times when reading (--open-noatime)",
+                            help_text::OPEN_NOATIME,
+                        )
+                        .on_disabled_hover_text(requires_text("--open-noatime"));
+                        let copy_as_supported = flag_supported(self.rsync_version.as_ref(), "--copy-as");
+                        ui.horizontal(|ui| {
+                            ui.add_enabled(copy_as_supported, egui::Label::new("Copy as (--copy-as):"))
+                                .on_disabled_hover_text(requires_text("--copy-as"));
+                            ui.add_enabled(
+                                copy_as_supported,
+                                egui::TextEdit::singleline(&mut self.options.copy_as).hint_text("USER[:GROUP]").desired_width(120.0),
+                            );
+                            help_icon(ui, help_text::COPY_AS);
+                        });
+                        if copy_as_supported && let Some(user) = self.options.copy_as() {
+                            let valid = user.split(':').count() <= 2
+                                && user.split(':').all(|x| !x.is_empty())
+                                && !user.contains(char::is_whitespace);
+                            if valid {
+                                ui.colored_label(
+                                    egui::Color32::from_rgb(255, 165, 0),
+                                    "rsync has to run as root (or be otherwise able to switch users) for this. \
+                                     Owners and groups kept with -a or set with --chown are applied separately, so check they agree with it.",
+                                );
+                            } else {
+                                ui.colored_label(egui::Color32::RED, "Expected USER or USER:GROUP");
+                            }
+                        }
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut self.options.super_mode, "Super-user operations (--super)").on_hover_text(
+                                "The receiving rsync tries to set owners, groups and devices as root would. \
+                                 Only use it with a destination you trust: whoever can write the source decides what ends up owned by whom.",
+                            );
+                            help_icon(ui, help_text::SUPER);
+                        });
+                        // -a includes --owner, which only takes effect when the remote side runs with privileges.
+                        // -g alone doesn't need them for groups the remote user is in, so it isn't flagged.
+                        if !self.options.super_mode
+                            && self.options.archive
+                            && split_remote(self.options.dest.trim()).is_some()
+                        {
+                            ui.horizontal(|ui| {
+                                ui.label("Keeping owners (-a includes --owner) on a remote destination needs root there.");
+                                if ui.small_button("Enable --super").clicked() {
+                                    self.options.super_mode = true;
+                                }
+                            });
+                        }
+                        let response = flag_checkbox(ui, true, &mut self.options.compress, "Compress (-z)", help_text::COMPRESS);
+                        self.override_marker(ui, &response, "compress", &overridden);
+                        if self.options.compress {
+                            ui.horizontal(|ui| {
+                                let supported = flag_supported(self.rsync_version.as_ref(), "--skip-compress");
+                                let mut enabled = self.options.skip_compress.is_some();
+                                let checkbox = ui
+                                    .add_enabled(supported, Checkbox::new(&mut enabled, "Skip compression for:"))
+                                    .on_disabled_hover_text(requires_text("--skip-compress"));
+                                if checkbox.changed() {
+                                    self.options.skip_compress = enabled.then(|| DEFAULT_SKIP_COMPRESS.to_string());
+                                }
+                                if let Some(suffixes) = &mut self.options.skip_compress {
+                                    ui.add_enabled(supported, egui::TextEdit::singleline(suffixes).hint_text("gz/jpg/mp4"));
+                                }
+                            });
+                        }
+                        let response = flag_checkbox(ui, true, &mut self.options.checksum, "Checksum (-c)", help_text::CHECKSUM);
+                        self.override_marker(ui, &response, "checksum", &overridden);
+                        flag_checkbox(ui, true, &mut self.options.update_only, "Skip newer files at destination (-u)", help_text::UPDATE);
+                        ui.horizontal(|ui| {
+                            if self.options.dry_run_only {
+                                ui.add_enabled(false, Checkbox::new(&mut true, "Dry Run (-n)"));
+                            } else {
+                                ui.checkbox(&mut self.options.dry_run, "Dry Run (-n)");
+                            }
+                            help_icon(ui, help_text::DRY_RUN);
+                            ui.checkbox(&mut self.options.dry_run_only, "Never write (dry-run only)")
+                                .on_hover_text("Saved with the profile; every run of it is a preview");
+                        });
+                        let writable = !self.options.dry_run_only;
+                        flag_checkbox(
+                            ui,
+                            writable,
+                            &mut self.options.move_files,
+                            "Move Files (--remove-source-files)",
+                            help_text::REMOVE_SOURCE_FILES,
+                        );
+                        if self.options.move_files {
+                            if self.options.is_dry_run() {
+                                ui.label("Dry run: no source files will be removed.");
+                            } else {
+                                ui.colored_label(egui::Color32::RED, "⚠ Source files will be deleted after a successful copy!");
+                            }
+                        }
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut self.options.partial, "Keep partial files (--partial)");
+                            help_icon(ui, help_text::PARTIAL);
+                            if self.options.partial {
+                                let mut enabled = self.options.partial_dir.is_some();
+                                if ui.checkbox(&mut enabled, "in directory:").changed() {
+                                    self.options.partial_dir = enabled.then(|| DEFAULT_PARTIAL_DIR.to_string());
+                                }
+                                if let Some(dir) = &mut self.options.partial_dir {
+                                    let response = ui.add(egui::TextEdit::singleline(dir).hint_text(DEFAULT_PARTIAL_DIR).desired_width(120.0));
+                                    outline_conflict(ui, &response, "--partial-dir", &conflicts);
+                                    if Path::new(dir.trim()).is_absolute() {
+                                        ui.colored_label(egui::Color32::RED, "Must be relative to the destination");
+                                    }
+                                }
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            let response = ui.add_enabled(writable, Checkbox::new(&mut self.options.delete, "Delete extraneous files (--delete)"));
+                            outline_conflict(ui, &response, "--delete", &conflicts);
+                            help_icon(ui, help_text::DELETE);
+                            if self.options.delete {
+                                let response = egui::ComboBox::from_id_salt("delete_timing")
+                                    .selected_text(self.options.delete_timing.label())
+                                    .show_ui(ui, |ui| {
+                                        for timing in DeleteTiming::ALL {
+                                            let flag = timing.as_arg().unwrap_or_default();
+                                            ui.add_enabled_ui(flag_supported(self.rsync_version.as_ref(), flag), |ui| {
+                                                ui.selectable_value(&mut self.options.delete_timing, timing, timing.label())
+                                                    .on_disabled_hover_text(requires_text(flag));
+                                            });
+                                        }
+                                    })
+                                    .response;
+                                outline_conflict(ui, &response, "--delete-delay", &conflicts);
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Hard-link unchanged files from (--link-dest):");
+                            ui.add(egui::TextEdit::singleline(&mut self.options.link_dest).hint_text("../previous").desired_width(160.0));
+                            help_icon(ui, help_text::LINK_DEST);
+                        });
+                        ui.add_enabled(!self.options.is_dry_run() && !self.options.move_files, Checkbox::new(&mut self.options.verify, "Verify after transfer"));
+                        if self.options.verify {
+                            ui.horizontal(|ui| {
+                                let mut enabled = self.options.verify_sample.is_some();
+                                let checkbox = ui
+                                    .add_enabled(self.options.spot_check_supported(), Checkbox::new(&mut enabled, "Spot-check only"))
+                                    .on_hover_text("Checksums a random sample of the transferred files instead of the whole tree")
+                                    .on_disabled_hover_text("Not available with Relative paths (-R) or a file list");
+                                if checkbox.changed() {
+                                    self.options.verify_sample = enabled.then_some(DEFAULT_VERIFY_SAMPLE);
+                                }
+                                if let Some(files) = &mut self.options.verify_sample {
+                                    ui.add(DragValue::new(files).range(1..=100000).suffix(" files"));
+                                }
+                            });
+                        }
+                        ui.add_enabled_ui(!self.options.is_dry_run() && !self.options.move_files, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("Compare SHA-256 hashes of");
+                                ui.add(DragValue::new(&mut self.options.verify_sample_pct).range(0..=100).suffix(" %"));
+                                ui.label("of the transferred files");
+                            })
+                            .response
+                            .on_hover_text("Reads a random share of the files on both sides after the transfer. Only for a local source and destination; 0 % turns it off.");
+                        });
+                        let response = flag_checkbox(ui, true, &mut self.options.human_readable, "Human-readable Stats (-h)", help_text::HUMAN_READABLE);
+                        self.override_marker(ui, &response, "human_readable", &overridden);
 
+                        let version = self.rsync_version.as_ref();
+                        flag_checkbox(
+                            ui,
+                            flag_supported(version, "--info=progress2"),
+                            &mut self.options.overall_progress,
+                            "Overall Progress Only (--info=progress2)",
+                            help_text::PROGRESS2,
+                        )
+                        .on_hover_text("Shows a single progress bar for the whole transfer instead of a per-file one. Recommended for many small files.")
+                        .on_disabled_hover_text(requires_text("--info=progress2"));
+                        ui.add_enabled(
+                            !self.options.delete,
+                            Checkbox::new(&mut self.options.fast_start, "Fast start (skip pre-scan)"),
+                        )
+                        .on_hover_text("Starts rsync right away instead of running a dry-run first. The overall progress appears once rsync has counted the files.")
+                        .on_disabled_hover_text("Runs with --delete always do the dry-run so the deletions can be confirmed.");
                         ui.horizontal(|ui| {
-                            ui.checkbox(&mut self.limit_bw, "Speed Limit:");
+                            ui.checkbox(&mut self.options.limit_bw, "Speed Limit:");
+                            help_icon(ui, help_text::BWLIMIT);
 
-                            ui.add_enabled_ui(self.limit_bw, |ui| {
-                                let bw_drag_value = DragValue::new(&mut self.bwlimit_kbps)
+                            ui.add_enabled_ui(self.options.limit_bw, |ui| {
+                                let bw_drag_value = DragValue::new(&mut self.options.bwlimit_kbps)
                                     .range(1..=1000000)
                                     .speed(10.0)
                                     .suffix(" KB/s");
@@ -455,51 +4979,334 @@ impl eframe::App for AppState {
                             });
                         });
 
-                        ui.collapsing("Excluded", |ui| {
-                            ui.label("Excluded (per-line):");
-                            ui.add_space(1f32);
-                            ui.text_edit_multiline(&mut self.excluded);
-                        });
+                        ui.collapsing("Filter rules", |ui| self.filter_rules_ui(ui));
 
-                        ui.collapsing("Included", |ui| {
-                            ui.label("Included (per-line):");
+                        ui.collapsing("Exclude patterns (one per line)", |ui| {
+                            ui.label("Passed with --exclude-from, so rsync's filter syntax applies:");
                             ui.add_space(1f32);
-                            ui.text_edit_multiline(&mut self.included);
+                            ui.add(egui::TextEdit::multiline(&mut self.options.exclude_from_content).code_editor());
                         });
 
-                        if ui.button("Run").clicked() {
-                            self.error_logs.clear();
-                            self.logs.clear();
-                            self.is_finished = false;
-                            self.current_progress = Progress::default();
-
-                            let mut dry_run = create_rsync_dry_run_command(self);
-                            let output = dry_run.output().context("Failed to run dry-run").unwrap();
-                            let result = String::from_utf8_lossy(&output.stdout).to_string();
-                            let result_err = String::from_utf8_lossy(&output.stderr).to_string();
-
-                            if !result_err.trim().is_empty() {
-                                self.error_logs.push_str(&result_err);
-                                self.error_logs.push('\n');
-                                if result_err.contains("Permission denied") {
-                                    self.error_logs.push_str("Access denied when connecting to the server via SSH. Please check if your SSH key is configured.\n");
-                                    return;
+                        flag_checkbox(ui, true, &mut self.options.files_from, "Transfer a list of files (--files-from)", help_text::FILES_FROM);
+                        if self.options.files_from {
+                            ui.horizontal(|ui| {
+                                ui.label("Existing list file:");
+                                ui.add(egui::TextEdit::singleline(&mut self.options.files_from_file).hint_text("leave empty to use the list below"));
+                            });
+                            ui.add_enabled_ui(self.options.files_from_file.trim().is_empty(), |ui| {
+                                ui.label(format!(
+                                    "Paths, one per line ({} listed). Absolute paths are sent relative to their common directory:",
+                                    file_list_entries(&self.options).len()
+                                ));
+                                ui.add(egui::TextEdit::multiline(&mut self.options.files_from_list).code_editor());
+                            });
+                        }
+
+                        let advanced = egui::CollapsingHeader::new("Advanced")
+                            .default_open(self.config.advanced_open)
+                            .show(ui, |ui| {
+                                ui.checkbox(&mut self.options.use_pty, "Run rsync in a pseudo-terminal")
+                                    .on_hover_text("For rsync builds that hold back or change their progress output when writing to a pipe.");
+                                flag_checkbox(
+                                    ui,
+                                    flag_supported(self.rsync_version.as_ref(), "--outbuf=L"),
+                                    &mut self.options.line_buffered,
+                                    "Line-buffered output (--outbuf=L)",
+                                    help_text::OUTBUF,
+                                )
+                                .on_hover_text("Use if the progress bar stalls and then jumps. Not needed on most systems.")
+                                .on_disabled_hover_text(requires_text("--outbuf=L"));
+
+                                let response = flag_checkbox(ui, true, &mut self.options.relative, "Relative paths (-R)", help_text::RELATIVE)
+                                    .on_hover_text("Recreates the source path, e.g. /home/me/docs, under the destination");
+                                outline_conflict(ui, &response, "-R", &conflicts);
+                                let response = flag_checkbox(
+                                    ui,
+                                    true,
+                                    &mut self.options.no_implied_dirs,
+                                    "Don't recreate parent directories (--no-implied-dirs)",
+                                    help_text::NO_IMPLIED_DIRS,
+                                );
+                                outline_conflict(ui, &response, "--no-implied-dirs", &conflicts);
+
+                                ui.horizontal(|ui| {
+                                    ui.label("Remote rsync path (--rsync-path):");
+                                    ui.add(egui::TextEdit::singleline(&mut self.options.remote_rsync_path).hint_text("rsync"))
+                                        .on_hover_text("Program started on the remote host, e.g. /opt/bin/rsync or \"sudo rsync\"");
+                                });
+
+                                ui.horizontal(|ui| {
+                                    ui.label("rsync binary:");
+                                    let response = ui.add(egui::TextEdit::singleline(&mut self.options.rsync_binary).hint_text("rsync"));
+                                    self.editing_rsync_binary = response.has_focus();
+                                    match &self.rsync_version {
+                                        Some(x) => ui.label(format!("{}.{}.{} (protocol {})", x.major, x.minor, x.patch, x.protocol)),
+                                        None => ui.colored_label(egui::Color32::RED, "Not found"),
+                                    };
+                                });
+
+                                ui.collapsing("System Priority", |ui| {
+                                    ui.checkbox(&mut self.options.low_priority, "Adjust rsync priority");
+                                    ui.add_enabled_ui(self.options.low_priority, |ui| {
+                                        ui.add(egui::Slider::new(&mut self.options.nice_level, -20..=19).text("Nice level"));
+                                        if self.options.nice_level < 0 {
+                                            ui.label("Negative nice levels require root and are ignored otherwise.");
+                                        }
+
+                                        egui::ComboBox::from_label("IO class (ionice)")
+                                            .selected_text(self.options.ionice_class.map(|x| x.label()).unwrap_or("Default"))
+                                            .show_ui(ui, |ui| {
+                                                ui.selectable_value(&mut self.options.ionice_class, None, "Default");
+                                                for class in [IoNiceClass::Idle, IoNiceClass::BestEffort, IoNiceClass::Realtime] {
+                                                    ui.selectable_value(&mut self.options.ionice_class, Some(class), class.label());
+                                                }
+                                            });
+                                        if self.options.ionice_class.is_some() && !*IONICE_AVAILABLE {
+                                            ui.label("ionice is not available, only the CPU priority will be changed.");
+                                        }
+                                    });
+                                });
+
+                                ui.horizontal(|ui| {
+                                    ui.label("Remote shell (-e):");
+                                    let hint = match self.options.env_value("RSYNC_RSH") {
+                                        Some(rsh) if !rsh.trim().is_empty() => format!("{} (from RSYNC_RSH)", rsh.trim()),
+                                        _ => "ssh (default)".to_string(),
+                                    };
+                                    ui.add(egui::TextEdit::singleline(&mut self.options.custom_rsh).hint_text(hint))
+                                        .on_hover_text("The full command rsync connects with, e.g. ssh -J jump@bastion to go through a jump host");
+                                    if ui.button("Use SSH (default)").clicked() {
+                                        self.options.custom_rsh = ssh_command(&self.options);
+                                    }
+                                    if ui.button("Clear").clicked() {
+                                        self.options.custom_rsh.clear();
+                                    }
+                                });
+
+                                let uses_ssh = custom_rsh(&self.options).is_none();
+                                if !uses_ssh {
+                                    ui.weak("The remote shell replaces the generated ssh command, so the IP version and connection sharing below don't apply to it; put options such as -4, -p, -i or -J in the command instead.");
+                                }
+                                ui.add_enabled_ui(uses_ssh, |ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.label("IP Version:");
+                                        ui.radio_value(&mut self.options.force_ipv, None, "Auto");
+                                        ui.radio_value(&mut self.options.force_ipv, Some(IpVersion::V4), "IPv4 only");
+                                        ui.radio_value(&mut self.options.force_ipv, Some(IpVersion::V6), "IPv6 only");
+                                    });
+                                    ui.checkbox(&mut self.options.use_ssh_multiplexing, "Reuse one SSH connection (ControlMaster)")
+                                        .on_hover_text("The dry-run opens the connection and the transfer reuses it, so you only authenticate once. It is closed when the run ends.");
+                                })
+                                .response
+                                .on_disabled_hover_text("Set these in the custom remote shell instead");
+
+                                ui.horizontal(|ui| {
+                                    ui.label("Bind address (--address):");
+                                    ui.text_edit_singleline(&mut self.options.bind_address);
+                                    egui::ComboBox::from_id_salt("bind_address_suggestions")
+                                        .selected_text("Detected")
+                                        .show_ui(ui, |ui| {
+                                            for address in &self.local_addresses {
+                                                let address = address.to_string();
+                                                if ui.selectable_label(self.options.bind_address == address, &address).clicked() {
+                                                    self.options.bind_address = address;
+                                                }
+                                            }
+                                        });
+                                });
+                                if !self.options.bind_address.trim().is_empty() && bind_address(&self.options).is_none() {
+                                    ui.colored_label(egui::Color32::RED, "Not a valid IP address");
+                                }
+
+                                ui.add_enabled_ui(uses_ssh, |ui| ui.horizontal(|ui| {
+                                    ui.checkbox(&mut self.options.ssh_retry, "Retry failed SSH connections:");
+                                    ui.add_enabled_ui(self.options.ssh_retry, |ui| {
+                                        ui.add(DragValue::new(&mut self.options.ssh_retry_attempts).range(1..=10).suffix(" attempts"));
+                                        ui.add(DragValue::new(&mut self.options.ssh_retry_delay_secs).range(1..=300).suffix(" s base delay"));
+                                    });
+                                }));
+
+                                ui.horizontal(|ui| {
+                                    let mut enabled = self.options.log_file.is_some();
+                                    if ui.checkbox(&mut enabled, "Log File (--log-file):").changed() {
+                                        self.options.log_file = enabled.then(String::new);
+                                    }
+                                    if let Some(log_file) = &mut self.options.log_file {
+                                        ui.add(egui::TextEdit::singleline(log_file).hint_text("/path/to/rsync.log"));
+                                    }
+                                });
+                                if self.options.log_file.is_some() {
+                                    ui.horizontal(|ui| {
+                                        ui.label("Log format (optional):");
+                                        ui.add(egui::TextEdit::singleline(&mut self.options.log_file_format).hint_text("%i %n%L"));
+                                    });
                                 }
+
+                                ui.collapsing("Hooks", |ui| {
+                                    ui.label("Shell commands run before and after the transfer:");
+                                    ui.horizontal(|ui| {
+                                        ui.label("Pre-transfer command:");
+                                        ui.text_edit_singleline(&mut self.options.pre_command);
+                                    });
+                                    ui.horizontal(|ui| {
+                                        ui.label("Post-transfer command:");
+                                        ui.text_edit_singleline(&mut self.options.post_command);
+                                    });
+                                    ui.label("The transfer is aborted if the pre-transfer command fails. The post-transfer command gets the rsync exit code in $RSYNC_EXIT_CODE.");
+                                });
+
+                                ui.collapsing("Inherited Environment", |ui| {
+                                    ui.label("Variables rsync and ssh would otherwise take from this app's environment:");
+                                    for key in PASSTHROUGH_VARS {
+                                        ui.horizontal(|ui| {
+                                            ui.monospace(key);
+                                            let inherited = match std::env::var(key) {
+                                                Ok(value) if is_secret(key, &value) => "***".to_string(),
+                                                Ok(value) => value,
+                                                Err(_) => "not set".to_string(),
+                                            };
+                                            ui.weak(inherited);
+                                        });
+                                        ui.horizontal(|ui| {
+                                            let current = self.options.env_overrides.get(key).cloned();
+                                            if ui.selectable_label(current.is_none(), "Inherit").clicked() {
+                                                self.options.env_overrides.remove(key);
+                                            }
+                                            let set = matches!(current, Some(EnvOverride::Set(_)));
+                                            if ui.selectable_label(set, "Set").clicked() && !set {
+                                                let value = std::env::var(key).ok().filter(|x| !is_secret(key, x)).unwrap_or_default();
+                                                self.options.env_overrides.insert(key.to_string(), EnvOverride::Set(value));
+                                            }
+                                            if ui.selectable_label(current == Some(EnvOverride::Unset), "Unset").clicked() {
+                                                self.options.env_overrides.insert(key.to_string(), EnvOverride::Unset);
+                                            }
+                                            if let Some(EnvOverride::Set(value)) = self.options.env_overrides.get_mut(key) {
+                                                let secret = is_secret(key, value);
+                                                ui.add(egui::TextEdit::singleline(value).password(secret));
+                                            }
+                                        });
+                                    }
+                                    if !self.options.custom_rsh.trim().is_empty() && self.options.env_value("RSYNC_RSH").is_some() {
+                                        ui.label("RSYNC_RSH is ignored while a remote shell is given above.");
+                                    }
+                                });
+
+                                ui.collapsing("Environment Variables", |ui| {
+                                    ui.label("Passed to rsync, e.g. RSYNC_PASSWORD for daemon transfers:");
+                                    let mut removed = None;
+                                    for (i, var) in self.options.env_vars.iter_mut().enumerate() {
+                                        ui.horizontal(|ui| {
+                                            ui.add(egui::TextEdit::singleline(&mut var.key).hint_text("NAME").desired_width(140.0));
+                                            let secret = var.is_secret();
+                                            ui.add(egui::TextEdit::singleline(&mut var.value).hint_text("value").password(secret));
+                                            if ui.button("✖").clicked() {
+                                                removed = Some(i);
+                                            }
+                                        });
+                                    }
+                                    if let Some(i) = removed {
+                                        self.options.env_vars.remove(i);
+                                    }
+                                    if ui.button("Add").clicked() {
+                                        self.options.env_vars.push(EnvVar::default());
+                                    }
+                                });
+                            });
+                        if advanced.header_response.clicked() {
+                            self.config.advanced_open = !self.config.advanced_open;
+                        }
+
+                        let can_run = !self.confirm_move && self.retry_at.is_none() && self.planned.is_none();
+                        egui::CollapsingHeader::new("Schedules")
+                            .default_open(self.profiles.profiles.iter().any(|x| x.schedule.is_some()))
+                            .show(ui, |ui| self.schedules_ui(ui, ctx));
+
+                        transfer_estimate_ui(ui, self.transfer_estimate, &self.history);
+
+                        ui.horizontal(|ui| {
+                            let blocked = blocks_run(&conflicts);
+                            let run = ui.add_enabled(can_run && !blocked, egui::Button::new("Run"));
+                            let run = if blocked {
+                                run.on_disabled_hover_text("Resolve the conflicting options first")
+                            } else {
+                                run
+                            };
+                            if run.clicked() {
+                                self.request_run(ctx, false);
+                            }
+                            self.source_estimate_ui(ui, ctx);
+                            if ui.add_enabled(can_run, egui::Button::new("Preview changes")).clicked() {
+                                self.preview_changes();
+                            }
+                            if ui
+                                .add_enabled(self.compare_rx.is_none(), egui::Button::new("Compare"))
+                                .on_hover_text("List what differs between source and destination without copying")
+                                .clicked()
+                            {
+                                self.start_compare(ctx);
                             }
+                            let target = ssh_target(&self.options)
+                                .filter(|_| custom_rsh(&self.options).is_none())
+                                .map(str::to_string);
+                            let testing = self.connection_test.is_some();
+                            if ui
+                                .add_enabled(target.is_some() && !testing, egui::Button::new("Test Connection"))
+                                .on_disabled_hover_text("Neither source nor destination is reached over the default SSH shell")
+                                .clicked()
+                            {
+                                let (tx, rx) = mpsc::channel();
+                                let options = self.options.clone();
+                                let ctx = ctx.clone();
+                                thread::spawn(move || {
+                                    let _ = tx.send(test_ssh_connection(&options, &target.unwrap()));
+                                    ctx.request_repaint();
+                                });
+                                self.connection_test = Some(rx);
+                                self.connection_status = None;
+                            }
+                            if ui.button("Export as script…").clicked() {
+                                let path = dirs::home_dir().unwrap_or_default().join("r-synced.sh");
+                                self.export_script = Some(path.to_string_lossy().to_string());
+                            }
+                        });
 
-                            let data = parse_rsync_stats(&result);
-                            let number_of_files = data.get("Number of files (regular)");
-                            if number_of_files.is_none() {
-                                self.error_logs.push_str("Could not determine the file count for the transfer.\n");
-                                self.error_logs.push_str(&result);
-                                self.error_logs.push('\n');
-                                return;
+                        if let Some(rx) = &self.connection_test
+                            && let Ok(result) = rx.try_recv()
+                        {
+                            self.connection_status = Some(result);
+                            self.connection_test = None;
+                        }
+                        if self.connection_test.is_some() {
+                            ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label("Testing connection…");
+                            });
+                        }
+                        match &self.connection_status {
+                            Some(Ok(())) => {
+                                ui.colored_label(egui::Color32::GREEN, "Connection OK ✓");
                             }
+                            Some(Err(e)) => {
+                                ui.colored_label(egui::Color32::RED, format!("Connection failed: {}", e));
+                            }
+                            None => {}
+                        }
+
+                        if let Some(at) = self.retry_at {
+                            ui.horizontal(|ui| {
+                                let remaining = at.saturating_duration_since(Instant::now()).as_secs_f32().ceil();
+                                ui.label(format!(
+                                    "Retrying in {}s (attempt {}/{})",
+                                    remaining, self.ssh_attempt, self.options.ssh_retry_attempts
+                                ));
+                                if ui.button("Cancel").clicked() {
+                                    self.retry_at = None;
+                                }
+                            });
+                        }
 
-                            let command = create_rsync_command(self);
-                            let rx = run_rsync(command, number_of_files.unwrap().replace(".", "").parse::<u64>().unwrap(), ctx.clone());
-                            self.progress = Some(rx.0);
-                            self.child = Some(rx.1);
+                        if let Some(notice) = &self.form_notice {
+                            ui.colored_label(egui::Color32::GREEN, notice);
                         }
 
                         if !self.error_logs.is_empty() {
@@ -511,8 +5318,9 @@ impl eframe::App for AppState {
                                     .auto_shrink([false; 2])
                                     .max_height(100.0)
                                     .show(ui, |ui| {
-                                        ui.label(&self.error_logs);
+                                        ui.label(self.error_logs.to_string());
                                     });
+                                self.error_report_buttons(ui);
                             });
                         }
                     });
@@ -522,16 +5330,400 @@ impl eframe::App for AppState {
 }
 
 fn main() -> eframe::Result {
+    let cli = Cli::parse();
+    if cli.no_gui {
+        std::process::exit(cli::run_headless(&cli));
+    }
+
+    let mut profiles = Profiles::load();
+    let cli_options = match cli.options(&mut profiles) {
+        Ok(x) => cli.has_options().then_some(x),
+        Err(e) => {
+            eprintln!("[r-synced] {}", e);
+            None
+        }
+    };
+
+    let config = Config::load();
+
+    let mut error_logs = ErrorLog::default();
+    for e in &profiles.load_errors {
+        error_logs.push_str(&format!("{}\n", e));
+    }
+
+    let mut viewport = egui::ViewportBuilder::default().with_inner_size([550.0, 650.0]);
+    if let Some(window) = config.window {
+        viewport = viewport
+            .with_inner_size([window.width, window.height])
+            .with_position([window.x, window.y]);
+    }
+
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size([550.0, 650.0]),
+        viewport,
         ..Default::default()
     };
 
-    eframe::run_native(
+    let event_loop = EventLoop::<eframe::UserEvent>::with_user_event().build()?;
+    let monitors = Monitors::default();
+    let app = eframe::create_native(
         "r-synced",
         options,
         Box::new(|_cc| {
-            Ok(Box::new(AppState::default()))
+            Ok(Box::new(AppState {
+                monitors: monitors.borrow().clone(),
+                history: History::load(),
+                config,
+                restore_draft: draft::load(),
+                selected_profile: cli.profile.clone().filter(|_| cli_options.is_some()),
+                options: cli_options.unwrap_or_else(|| profiles.defaults.options.clone()),
+                error_logs,
+                profiles,
+                local_addresses: if_addrs::get_if_addrs()
+                    .map(|x| {
+                        x.iter()
+                            .filter(|x| !x.is_loopback())
+                            .map(|x| x.ip())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                ..Default::default()
+            }))
         }),
-    )
+        &event_loop,
+    );
+    event_loop.run_app(&mut MonitorProbe {
+        app,
+        monitors: monitors.clone(),
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Overall progress of each progress line `script` prints, for a dry-run that found `files_count` files.
+    fn total_progress(script: &str, files_count: u64) -> Vec<f32> {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script);
+        let (rx, _) =
+            run_rsync(cmd, files_count, 0, false, None, egui::Context::default()).unwrap();
+        rx.iter()
+            .filter_map(|x| match x {
+                StateMessage::Progress(x) => Some(x.total_progress),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn follows_incremental_recursion_counters() {
+        // The dry-run counted 4 files; rsync's ir-chk total grows as it scans.
+        let script = r"printf '>f+++++++++ 100 a\n'
+            printf '    100 100%%    1,00MB/s    0:00:00 (xfr#1, ir-chk=9/10)\r'
+            printf '     50  50%%    1,00MB/s    0:00:00\r'
+            printf '    100 100%%    1,00MB/s    0:00:00 (xfr#2, ir-chk=10/20)\r'
+            printf '    100 100%%    1,00MB/s    0:00:00 (xfr#3, to-chk=0/20)\n'";
+        assert_eq!(total_progress(script, 4), [0.1, 0.1, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn falls_back_to_the_dry_run_count() {
+        let script = r"printf '>f+++++++++ 100 a\n'
+            printf '    100 100%%    1,00MB/s    0:00:00\n'
+            printf '>f+++++++++ 100 b\n'
+            printf '     50  50%%    1,00MB/s    0:00:00\n'";
+        assert_eq!(total_progress(script, 4), [0.25, 0.5]);
+
+        // A fast start has no dry-run count, only rsync's own.
+        let script = r"printf '    100 100%%    1,00MB/s    0:00:00 (xfr#1, to-chk=3/4)\n'";
+        assert_eq!(total_progress(script, 0), [0.25]);
+    }
+
+    /// rsync only ends progress updates with \r, so the itemized and `sender removed`
+    /// lines that follow one arrive in the same chunk.
+    #[test]
+    fn splits_progress_interleaved_with_other_lines() {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(
+            r"printf '>f+++++++++ 100 a\n     50  50%%    1,00MB/s    0:00:00\r'
+            printf '    100 100%%    1,00MB/s    0:00:00 (xfr#1, to-chk=1/2)\nsender removed a\n>f+++++++++ 10 b c\n'
+            printf '     10 100%%    1,00MB/s    0:00:00 (xfr#2, to-chk=0/2)\r*deleting   old\nsender removed b c\n'",
+        );
+        let (rx, _) = run_rsync(cmd, 2, 1, false, None, egui::Context::default()).unwrap();
+        let events: Vec<_> = rx
+            .iter()
+            .filter_map(|x| match x {
+                StateMessage::Progress(x) => Some(format!("progress {}", x.progress)),
+                StateMessage::NextFile(x) => Some(format!("next {}", x.line)),
+                StateMessage::SourceRemoved(_) => Some("removed".to_string()),
+                StateMessage::Deleted(_) => Some("deleted".to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                "next a",
+                "progress 0.5",
+                "progress 1",
+                "removed",
+                "next b c",
+                "progress 1",
+                "deleted",
+                "removed"
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_spaces_in_the_next_file_name() {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(r"printf '>f+++++++++ 52340 My Documents/report final.pdf\n'");
+        let (rx, _) = run_rsync(cmd, 1, 0, false, None, egui::Context::default()).unwrap();
+        let next: Vec<_> = rx
+            .iter()
+            .filter_map(|x| match x {
+                StateMessage::NextFile(x) => Some((x.line, x.size)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(next, [("My Documents/report final.pdf".to_string(), 52340)]);
+    }
+
+    #[test]
+    fn spot_checks_relative_to_the_source_directory() {
+        assert_eq!(spot_check_source("host:"), "host:");
+        assert_eq!(spot_check_source("host:dir"), "host:");
+        assert_eq!(spot_check_source("me@host:dir/sub"), "me@host:dir/");
+        assert_eq!(spot_check_source("host::module/dir"), "host::module/");
+        assert_eq!(
+            spot_check_source("rsync://host/module/dir"),
+            "rsync://host/module/"
+        );
+        assert_eq!(spot_check_source("photos"), "./");
+        assert_eq!(spot_check_source("/home/me/photos"), "/home/me/");
+        assert_eq!(spot_check_source(" host:dir/ "), "host:dir/");
+    }
+
+    #[test]
+    fn checks_the_log_file_without_creating_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("rsync.log");
+        assert!(check_writable(&log).is_ok());
+        assert!(!log.exists());
+        assert!(check_writable(&dir.path().join("missing").join("rsync.log")).is_err());
+    }
+
+    #[test]
+    fn tells_a_missing_source_from_an_empty_one() {
+        let missing = "rsync: [sender] link_stat \"/home/me/Photos\" failed: No such file or directory (2)\n\
+                       rsync error: some files/attrs were not transferred (see previous errors) (code 23) at main.c(1338) [sender=3.2.7]\n";
+        assert!(is_missing_source(Some(23), missing, "/home/me/Photos"));
+        assert!(is_missing_source(
+            Some(3),
+            "rsync: change_dir \"/home/me/Photos\" failed: No such file or directory (2)\n",
+            "/home/me/Photos/"
+        ));
+        assert!(is_missing_source(
+            Some(23),
+            "rsync: [sender] change_dir \"/home/me/Photos\" failed: No such file or directory (2)\n",
+            "nas:/home/me/Photos/"
+        ));
+        // A file vanishing mid-run isn't the source missing.
+        assert!(!is_missing_source(
+            Some(24),
+            "file has vanished: \"/home/me/Photos/tmp\"\n",
+            "/home/me/Photos/"
+        ));
+        assert!(!is_missing_source(Some(0), missing, "/home/me/Photos"));
+        // The receiver failing to enter a destination directory.
+        assert!(!is_missing_source(
+            Some(23),
+            "rsync: [Receiver] change_dir#3 \"/dest/Photos\" failed: No such file or directory (2)\n",
+            "/home/me/Photos"
+        ));
+        // One --files-from entry that no longer exists.
+        assert!(!is_missing_source(
+            Some(23),
+            "rsync: [sender] link_stat \"/home/me/Photos/2019/gone.jpg\" failed: No such file or directory (2)\n",
+            "/home/me/Photos/"
+        ));
+
+        // An empty source dry-runs cleanly and reports no regular files.
+        let empty = "\
+Number of files: 1 (dir: 1)
+Number of created files: 0
+Number of deleted files: 0
+Number of regular files transferred: 0
+Total file size: 0 bytes
+Total transferred file size: 0 bytes
+
+sent 58 bytes  received 19 bytes  154.00 bytes/sec
+total size is 0  speedup is 0.00 (DRY RUN)
+";
+        assert!(!is_missing_source(Some(0), "", "/home/me/Photos/"));
+        let stats = parse_rsync_stats(empty);
+        assert_eq!(stats["Number of files (regular)"], "0");
+        assert_eq!(stats["Number of files (directories)"], "1");
+    }
+
+    #[test]
+    fn parses_the_summary_of_a_real_run() {
+        let output = "\
+Number of files: 4 (reg: 3, dir: 1)
+Number of created files: 3 (reg: 3)
+Number of deleted files: 0
+Number of regular files transferred: 3
+Total file size: 1,234,567 bytes
+Total transferred file size: 1,234,567 bytes
+Literal data: 1,234,567 bytes
+Matched data: 0 bytes
+File list size: 0
+File list generation time: 0.001 seconds
+File list transfer time: 0.000 seconds
+Total bytes sent: 1,235,012
+Total bytes received: 76
+
+sent 1,235,012 bytes  received 76 bytes  2,470,176.00 bytes/sec
+total size is 1,234,567  speedup is 1.00
+";
+        let stats = parse_rsync_stats(output);
+        assert_eq!(stats["Total size (summary)"], "1234567");
+        assert_eq!(stats["Speedup"], "1.00");
+        assert!(!stats.contains_key("Run type"));
+
+        let dry_run = parse_rsync_stats("total size is 0  speedup is 0.00 (DRY RUN)\n");
+        assert_eq!(dry_run["Run type"], "DRY RUN");
+    }
+
+    #[test]
+    fn parses_human_readable_sizes_in_either_case() {
+        let output = "\
+Total file size: 1.23k bytes
+Literal data: 5,00M bytes
+Total bytes sent: 2.50K
+total size is 1.23k  speedup is 1.00
+";
+        let stats = parse_rsync_stats(output);
+        assert_eq!(stats["Total file size"], "1230 bytes");
+        assert_eq!(stats["Literal data"], "5000000 bytes");
+        assert_eq!(stats["Total bytes sent"], "2500");
+        assert_eq!(stats["Total size (summary)"], "1230");
+    }
+
+    #[test]
+    fn only_blocks_runs_on_real_conflicts() {
+        let flags = |options: &RsyncOptions| {
+            check_conflicts(options)
+                .iter()
+                .map(|x| (x.a, x.blocking))
+                .collect::<Vec<_>>()
+        };
+
+        assert!(flags(&RsyncOptions::default()).is_empty());
+
+        // Without -R the flag is only pointless, so the run may go ahead.
+        let options = RsyncOptions {
+            no_implied_dirs: true,
+            ..Default::default()
+        };
+        assert_eq!(flags(&options), [("--no-implied-dirs", false)]);
+        assert!(!blocks_run(&check_conflicts(&options)));
+        let options = RsyncOptions {
+            no_implied_dirs: true,
+            files_from: true,
+            ..Default::default()
+        };
+        assert!(flags(&options).is_empty());
+
+        let options = RsyncOptions {
+            delete: true,
+            no_implied_dirs: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            flags(&options),
+            [("--delete", true), ("--no-implied-dirs", false)]
+        );
+        assert!(blocks_run(&check_conflicts(&options)));
+
+        let options = RsyncOptions {
+            archive: true,
+            delete: true,
+            delete_timing: DeleteTiming::Delay,
+            partial: true,
+            partial_dir: Some("/tmp/partial".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(flags(&options), [("--delete-delay", true)]);
+        let options = RsyncOptions {
+            partial_dir: Some(".partial".to_string()),
+            ..options
+        };
+        assert!(flags(&options).is_empty());
+    }
+
+    #[test]
+    fn emits_filter_rules_in_list_order() {
+        let rule = |kind, pattern: &str| FilterRule {
+            kind,
+            pattern: pattern.to_string(),
+        };
+        let options = RsyncOptions {
+            filter_rules: vec![
+                rule(FilterKind::Include, "*.jpg"),
+                rule(FilterKind::Exclude, ""),
+                rule(FilterKind::Exclude, "cache/"),
+                rule(FilterKind::Include, "cache/keep"),
+                rule(FilterKind::Exclude, "*"),
+            ],
+            ..Default::default()
+        };
+        let mut cmd = Command::new("rsync");
+        push_selection_args(&mut cmd, &options, ListPaths::default());
+
+        let args: Vec<_> = cmd.get_args().map(|x| x.to_str().unwrap()).collect();
+        assert_eq!(
+            args,
+            [
+                "--include",
+                "*.jpg",
+                "--exclude",
+                "cache/",
+                "--include",
+                "cache/keep",
+                "--exclude",
+                "*"
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_stderr_written_after_stdout_closes() {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg("echo started; exec 1>&-; sleep 0.2; for i in 1 2 3; do echo \"error $i\" >&2; done; exit 23");
+        let (rx, _) = run_rsync(cmd, 0, 0, false, None, egui::Context::default()).unwrap();
+
+        let mut errors = Vec::new();
+        let mut finished = None;
+        for message in rx {
+            match message {
+                StateMessage::Error(x) => {
+                    assert!(
+                        finished.is_none(),
+                        "\"{}\" arrived after the run finished",
+                        x.line
+                    );
+                    errors.push(x.line);
+                }
+                StateMessage::FinishedWithStatus(code) => finished = Some(code),
+                _ => {}
+            }
+        }
+        assert_eq!(errors, ["error 1", "error 2", "error 3"]);
+        assert_eq!(finished, Some(23));
+    }
 }
@@ -0,0 +1,86 @@
+use eframe::egui;
+use eframe::{EframeWinitApplication, UserEvent};
+use std::cell::RefCell;
+use std::rc::Rc;
+use winit::application::ApplicationHandler;
+use winit::event_loop::ActiveEventLoop;
+use winit::monitor::MonitorHandle;
+
+/// Monitor rects in logical points, primary first. egui only reports the size
+/// of the current monitor, not where it sits, so this is read from winit.
+pub type Monitors = Rc<RefCell<Vec<egui::Rect>>>;
+
+/// Wraps the eframe application to note the connected monitors before eframe
+/// creates the window and the app.
+pub struct MonitorProbe<'a> {
+    pub app: EframeWinitApplication<'a>,
+    pub monitors: Monitors,
+}
+
+fn logical_rect(monitor: &MonitorHandle) -> egui::Rect {
+    let scale = monitor.scale_factor();
+    let position = monitor.position().to_logical::<f32>(scale);
+    let size = monitor.size().to_logical::<f32>(scale);
+    egui::Rect::from_min_size(
+        egui::pos2(position.x, position.y),
+        egui::vec2(size.width, size.height),
+    )
+}
+
+impl ApplicationHandler<UserEvent> for MonitorProbe<'_> {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let primary = event_loop.primary_monitor();
+        let mut monitors: Vec<_> = primary.iter().map(logical_rect).collect();
+        monitors.extend(
+            event_loop
+                .available_monitors()
+                .filter(|x| Some(x) != primary.as_ref())
+                .map(|x| logical_rect(&x)),
+        );
+        *self.monitors.borrow_mut() = monitors;
+
+        self.app.resumed(event_loop);
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window_id: winit::window::WindowId,
+        event: winit::event::WindowEvent,
+    ) {
+        self.app.window_event(event_loop, window_id, event);
+    }
+
+    fn new_events(&mut self, event_loop: &ActiveEventLoop, cause: winit::event::StartCause) {
+        self.app.new_events(event_loop, cause);
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: UserEvent) {
+        self.app.user_event(event_loop, event);
+    }
+
+    fn device_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        device_id: winit::event::DeviceId,
+        event: winit::event::DeviceEvent,
+    ) {
+        self.app.device_event(event_loop, device_id, event);
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        self.app.about_to_wait(event_loop);
+    }
+
+    fn suspended(&mut self, event_loop: &ActiveEventLoop) {
+        self.app.suspended(event_loop);
+    }
+
+    fn exiting(&mut self, event_loop: &ActiveEventLoop) {
+        self.app.exiting(event_loop);
+    }
+
+    fn memory_warning(&mut self, event_loop: &ActiveEventLoop) {
+        self.app.memory_warning(event_loop);
+    }
+}
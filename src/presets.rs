@@ -0,0 +1,82 @@
+use crate::options::{DeleteTiming, RsyncOptions};
+
+/// A named combination of flags set in one click. Options a preset has no
+/// opinion on are left as they are.
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Asked once before the preset is first applied.
+    pub confirmation: Option<&'static str>,
+    archive: bool,
+    delete: Option<DeleteTiming>,
+    checksum: Option<bool>,
+    update_only: Option<bool>,
+    clear_filters: bool,
+    /// Hard-link unchanged files against the previous snapshot.
+    pub link_previous: bool,
+}
+
+pub const MIRROR: Preset = Preset {
+    name: "Mirror",
+    description: "Make the destination an exact copy of the source, deleting whatever the source doesn't have",
+    confirmation: Some(
+        "Mirror mode will delete files at the destination that don't exist at the source. Continue?",
+    ),
+    archive: true,
+    delete: Some(DeleteTiming::After),
+    checksum: Some(false),
+    update_only: Some(false),
+    clear_filters: true,
+    link_previous: false,
+};
+
+pub const BACKUP: Preset = Preset {
+    name: "Backup",
+    description: "Copy into a new snapshot, hard-linking files that haven't changed since the previous one",
+    confirmation: None,
+    archive: true,
+    delete: None,
+    checksum: None,
+    update_only: None,
+    clear_filters: false,
+    link_previous: true,
+};
+
+pub const SYNC: Preset = Preset {
+    name: "Sync",
+    description: "Copy new and changed files, skipping any that are newer at the destination",
+    confirmation: None,
+    archive: true,
+    delete: None,
+    checksum: None,
+    update_only: Some(true),
+    clear_filters: false,
+    link_previous: false,
+};
+
+pub const PRESETS: [Preset; 3] = [MIRROR, BACKUP, SYNC];
+
+impl Preset {
+    /// `previous_snapshot` is the destination of the last backup of this source, if there was one.
+    pub fn apply(&self, options: &mut RsyncOptions, previous_snapshot: Option<&str>) {
+        options.archive = self.archive;
+        if let Some(timing) = self.delete {
+            options.delete = true;
+            options.delete_timing = timing;
+        }
+        if let Some(checksum) = self.checksum {
+            options.checksum = checksum;
+        }
+        if let Some(update_only) = self.update_only {
+            options.update_only = update_only;
+        }
+        if self.clear_filters {
+            options.filter_rules.clear();
+        }
+        if self.link_previous
+            && let Some(previous) = previous_snapshot
+        {
+            options.link_dest = previous.to_string();
+        }
+    }
+}
@@ -0,0 +1,387 @@
+use crate::defaults::GlobalDefaults;
+use crate::options::RsyncOptions;
+use crate::schedule::Schedule;
+use anyhow::Context;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Outcome of the most recent run of a profile.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LastRun {
+    pub started_at: DateTime<Local>,
+    pub exit_code: Option<i32>,
+    /// Started by the scheduler rather than by hand.
+    pub automatic: bool,
+}
+
+#[derive(Clone)]
+pub struct Profile {
+    pub name: String,
+    /// The global defaults with this profile's own values laid over them.
+    pub options: RsyncOptions,
+    pub schedule: Option<Schedule>,
+    /// Where the schedule counts from: the last run, or when it was set up.
+    pub schedule_anchor: Option<DateTime<Local>>,
+    pub skip_next: bool,
+    pub last_run: Option<LastRun>,
+}
+
+/// A profile as it is written to disk: only the options that differ from
+/// the global defaults are kept, so the profile follows the rest of them.
+#[derive(Serialize, Deserialize)]
+struct StoredProfile {
+    name: String,
+    /// Missing in profiles saved before there were defaults; their options are complete.
+    #[serde(default)]
+    inherits_defaults: bool,
+    #[serde(default)]
+    options: Map<String, Value>,
+    /// Options that differ by being unset, which TOML has no value for.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    unset: Vec<String>,
+    #[serde(default)]
+    schedule: Option<Schedule>,
+    #[serde(default)]
+    schedule_anchor: Option<DateTime<Local>>,
+    #[serde(default)]
+    skip_next: bool,
+    #[serde(default)]
+    last_run: Option<LastRun>,
+}
+
+impl StoredProfile {
+    fn new(profile: &Profile, defaults: &GlobalDefaults) -> StoredProfile {
+        let (unset, options): (Vec<_>, Vec<_>) = defaults
+            .deviations(&profile.options)
+            .into_iter()
+            .partition(|(_, value)| value.is_null());
+
+        StoredProfile {
+            name: profile.name.clone(),
+            inherits_defaults: true,
+            options: options.into_iter().collect(),
+            unset: unset.into_iter().map(|(key, _)| key).collect(),
+            schedule: profile.schedule,
+            schedule_anchor: profile.schedule_anchor,
+            skip_next: profile.skip_next,
+            last_run: profile.last_run.clone(),
+        }
+    }
+
+    fn into_profile(self, defaults: &GlobalDefaults) -> Profile {
+        let options = if self.inherits_defaults {
+            let mut deviations = self.options;
+            for key in self.unset {
+                deviations.insert(key, Value::Null);
+            }
+            defaults.merge(&deviations)
+        } else {
+            serde_json::from_value(Value::Object(self.options)).unwrap_or_default()
+        };
+
+        Profile {
+            name: self.name,
+            options,
+            schedule: self.schedule,
+            schedule_anchor: self.schedule_anchor,
+            skip_next: self.skip_next,
+            last_run: self.last_run,
+        }
+    }
+}
+
+impl Profile {
+    pub fn next_run(&self) -> Option<DateTime<Local>> {
+        let schedule = self.schedule?;
+        Some(schedule.next_run(self.schedule_anchor?))
+    }
+}
+
+/// Named sets of options the user saved, optionally run on a schedule. Each
+/// one is kept in its own TOML file, and the list is sorted by name.
+#[derive(Default)]
+pub struct Profiles {
+    pub profiles: Vec<Profile>,
+    pub defaults: GlobalDefaults,
+    /// Profile files that couldn't be read, left in place for the user to fix.
+    pub load_errors: Vec<String>,
+    /// The files behind `profiles`, so saving only removes those of deleted or renamed ones.
+    files: Vec<PathBuf>,
+}
+
+fn profiles_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|x| x.join("r-synced").join("profiles"))
+}
+
+/// Where all profiles were kept in one file before they got a file each.
+fn legacy_profiles_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|x| x.join("r-synced").join("profiles.json"))
+}
+
+/// The profile name with characters that can't appear in a file name replaced.
+fn file_name(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| {
+            if c == '/' || c == '\\' || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    format!("{}.toml", stem.trim_start_matches('.'))
+}
+
+fn read_profile(path: &Path) -> anyhow::Result<StoredProfile> {
+    let data = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the profile {}", path.display()))?;
+    toml::from_str(&data).with_context(|| format!("Failed to parse the profile {}", path.display()))
+}
+
+#[derive(Deserialize)]
+struct LegacyProfiles {
+    profiles: Vec<StoredProfile>,
+}
+
+impl Profiles {
+    pub fn load() -> Profiles {
+        let mut profiles = Profiles {
+            defaults: GlobalDefaults::load(),
+            ..Default::default()
+        };
+
+        if let Some(entries) = profiles_dir().and_then(|dir| fs::read_dir(dir).ok()) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_none_or(|x| x != "toml") {
+                    continue;
+                }
+
+                match read_profile(&path) {
+                    Ok(profile) => {
+                        profiles
+                            .profiles
+                            .push(profile.into_profile(&profiles.defaults));
+                        profiles.files.push(path);
+                    }
+                    Err(e) => profiles.load_errors.push(format!("{:#}", e)),
+                }
+            }
+        }
+
+        if profiles.profiles.is_empty()
+            && let Some(legacy) = legacy_profiles_path()
+            && let Some(data) = fs::read_to_string(&legacy).ok()
+            && let Ok(legacy_profiles) = serde_json::from_str::<LegacyProfiles>(&data)
+        {
+            profiles.profiles = legacy_profiles
+                .profiles
+                .into_iter()
+                .map(|x| x.into_profile(&profiles.defaults))
+                .collect();
+            if profiles.save().is_ok() {
+                let _ = fs::remove_file(legacy);
+            }
+        }
+
+        profiles.sort();
+        profiles
+    }
+
+    /// Writes every profile and removes the files of deleted or renamed ones.
+    /// Files that weren't loaded, like ones that failed to parse, are left alone.
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        let dir = profiles_dir().context("Could not determine the config directory")?;
+        fs::create_dir_all(&dir).context("Failed to create the profiles directory")?;
+
+        let mut written = Vec::new();
+        for profile in &self.profiles {
+            let path = dir.join(file_name(&profile.name));
+            let data = toml::to_string_pretty(&StoredProfile::new(profile, &self.defaults))
+                .with_context(|| format!("Failed to serialize the profile {}", profile.name))?;
+            fs::write(&path, data)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            written.push(path);
+        }
+
+        for path in &self.files {
+            if !written.contains(path) && path.exists() {
+                fs::remove_file(path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+        self.files = written;
+
+        Ok(())
+    }
+
+    /// Replaces the global defaults. Every profile keeps its own values and
+    /// takes the new defaults for everything else.
+    pub fn set_defaults(&mut self, defaults: GlobalDefaults) -> anyhow::Result<()> {
+        for profile in &mut self.profiles {
+            let deviations = self.defaults.deviations(&profile.options);
+            profile.options = defaults.merge(&deviations);
+        }
+        self.defaults = defaults;
+
+        self.defaults.save()?;
+        self.save()
+    }
+
+    fn sort(&mut self) {
+        self.profiles.sort_by_key(|x| x.name.to_lowercase());
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Profile> {
+        self.profiles.iter_mut().find(|x| x.name == name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.profiles
+            .iter()
+            .any(|x| file_name(&x.name) == file_name(name))
+    }
+
+    /// Replaces the options of an existing profile with the same name, keeping its schedule.
+    pub fn upsert(&mut self, name: &str, options: RsyncOptions) {
+        match self.get_mut(name) {
+            Some(profile) => profile.options = options,
+            None => {
+                self.profiles.push(Profile {
+                    name: name.to_string(),
+                    options,
+                    schedule: None,
+                    schedule_anchor: None,
+                    skip_next: false,
+                    last_run: None,
+                });
+                self.sort();
+            }
+        }
+    }
+
+    /// Fails when another profile already has the new name.
+    pub fn rename(&mut self, from: &str, to: &str) -> bool {
+        if from == to {
+            return true;
+        }
+        if self.contains(to) && file_name(from) != file_name(to) {
+            return false;
+        }
+
+        let Some(profile) = self.get_mut(from) else {
+            return false;
+        };
+        profile.name = to.to_string();
+        self.sort();
+        true
+    }
+
+    /// Copies a profile as "<name> (copy)", without its schedule or last run.
+    /// Returns the name of the copy.
+    pub fn duplicate(&mut self, name: &str) -> Option<String> {
+        let profile = self.profiles.iter().find(|x| x.name == name)?;
+
+        let mut copy_name = format!("{} (copy)", name);
+        let mut n = 2;
+        while self.contains(&copy_name) {
+            copy_name = format!("{} (copy {})", name, n);
+            n += 1;
+        }
+
+        let options = profile.options.clone();
+        self.upsert(&copy_name, options);
+        Some(copy_name)
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.profiles.retain(|x| x.name != name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::{DeleteTiming, FilterKind, FilterRule};
+
+    fn json(options: &RsyncOptions) -> Value {
+        serde_json::to_value(options).unwrap()
+    }
+
+    fn round_trip(profile: &Profile, defaults: &GlobalDefaults) -> Profile {
+        let data = toml::to_string_pretty(&StoredProfile::new(profile, defaults)).unwrap();
+        toml::from_str::<StoredProfile>(&data)
+            .unwrap()
+            .into_profile(defaults)
+    }
+
+    #[test]
+    fn profile_survives_a_round_trip() {
+        let defaults = GlobalDefaults::from_options(&RsyncOptions {
+            archive: true,
+            compress: true,
+            ..Default::default()
+        });
+        let profile = Profile {
+            name: "photos".to_string(),
+            options: RsyncOptions {
+                src: "/home/me/photos/".to_string(),
+                dest: "backup:photos".to_string(),
+                compress: false,
+                delete: true,
+                delete_timing: DeleteTiming::After,
+                // Unset where the default has a value.
+                skip_compress: None,
+                partial_dir: Some(".partial".to_string()),
+                filter_rules: vec![FilterRule {
+                    kind: FilterKind::Exclude,
+                    pattern: "*.tmp".to_string(),
+                }],
+                ..defaults.options.clone()
+            },
+            schedule: Some(Schedule::Daily {
+                hour: 3,
+                minute: 30,
+            }),
+            schedule_anchor: Some(Local::now()),
+            skip_next: true,
+            last_run: None,
+        };
+
+        let loaded = round_trip(&profile, &defaults);
+        assert_eq!(json(&loaded.options), json(&profile.options));
+        assert_eq!(loaded.name, profile.name);
+        assert!(loaded.schedule == profile.schedule);
+        assert_eq!(loaded.schedule_anchor, profile.schedule_anchor);
+        assert!(loaded.skip_next);
+
+        // Only the deviations are stored, so the rest follows new defaults.
+        let stored = StoredProfile::new(&profile, &defaults);
+        assert!(!stored.options.contains_key("archive"));
+        assert_eq!(stored.unset, ["skip_compress"]);
+        let changed = GlobalDefaults::from_options(&RsyncOptions {
+            checksum: true,
+            ..defaults.options.clone()
+        });
+        let followed = stored.into_profile(&changed);
+        assert!(followed.options.checksum);
+        assert!(!followed.options.compress);
+    }
+
+    #[test]
+    fn defaults_survive_a_round_trip() {
+        let defaults = GlobalDefaults::from_options(&RsyncOptions {
+            archive: true,
+            human_readable: true,
+            bwlimit_kbps: 500,
+            ..Default::default()
+        });
+        let loaded: GlobalDefaults =
+            toml::from_str(&toml::to_string_pretty(&defaults).unwrap()).unwrap();
+        assert_eq!(json(&loaded.options), json(&defaults.options));
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::Context;
+use chrono::Local;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Everything rsync prints during a run, teed to a timestamped file under the
+/// data directory. Stops writing after the first I/O error so a full disk only
+/// costs the on-disk copy, not the transfer.
+pub struct RawLog {
+    path: PathBuf,
+    writer: Option<BufWriter<File>>,
+    last_flush: Instant,
+}
+
+fn logs_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|x| x.join("r-synced").join("logs"))
+}
+
+impl RawLog {
+    pub fn create(run_id: &str) -> anyhow::Result<RawLog> {
+        let dir = logs_dir().context("Could not determine the data directory")?;
+        fs::create_dir_all(&dir).context("Failed to create the log directory")?;
+
+        let path = dir.join(format!(
+            "{}-{}.log",
+            Local::now().format("%Y-%m-%dT%H-%M-%S"),
+            run_id
+        ));
+        let file =
+            File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+
+        let mut log = RawLog {
+            path,
+            writer: Some(BufWriter::new(file)),
+            last_flush: Instant::now(),
+        };
+        log.write_line(&format!("# r-synced run #{}", run_id), false)?;
+
+        Ok(log)
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Returns an error only for the write that failed first; later lines are dropped silently.
+    pub fn write_line(&mut self, line: &str, stderr: bool) -> anyhow::Result<()> {
+        let Some(writer) = &mut self.writer else {
+            return Ok(());
+        };
+
+        let prefix = if stderr { "[err] " } else { "" };
+        let mut result = writeln!(writer, "{}{}", prefix, line);
+        if result.is_ok() && self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            result = writer.flush();
+            self.last_flush = Instant::now();
+        }
+
+        if let Err(e) = result {
+            self.writer = None;
+            return Err(e).with_context(|| {
+                format!(
+                    "Stopped writing the raw log {}, output is kept in memory only",
+                    self.path.display()
+                )
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) {
+        if let Some(writer) = &mut self.writer
+            && writer.flush().is_err()
+        {
+            self.writer = None;
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::utils::split_remote;
+use lazy_static::lazy_static;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref ACTIVE: Mutex<HashSet<(String, String)>> = Mutex::new(HashSet::new());
+}
+
+/// Remote hostnames are case-insensitive, local paths are resolved so that
+/// `./backup`, `backup/` and `/home/me/backup` all map to the same key.
+pub fn normalize_endpoint(endpoint: &str) -> String {
+    let endpoint = endpoint.trim();
+
+    if let Some((host, path)) = split_remote(endpoint) {
+        return format!("{}:{}", host.to_lowercase(), path.trim_end_matches('/'));
+    }
+
+    let path = Path::new(endpoint);
+    let canonical = fs::canonicalize(path).or_else(|e| {
+        // The destination may not exist yet, so fall back to its parent.
+        match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => {
+                let parent = if parent.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    parent
+                };
+                fs::canonicalize(parent).map(|x| x.join(name))
+            }
+            _ => Err(e),
+        }
+    });
+
+    match canonical {
+        Ok(x) => x.to_string_lossy().to_string(),
+        Err(_) => endpoint.trim_end_matches('/').to_string(),
+    }
+}
+
+/// Marks a src/dest pair as busy for as long as it is alive, so the entry is
+/// released on every exit path of a transfer, including cancellation.
+pub struct ActiveTransfer {
+    key: (String, String),
+}
+
+impl ActiveTransfer {
+    pub fn acquire(src: &str, dest: &str) -> Option<ActiveTransfer> {
+        let key = (normalize_endpoint(src), normalize_endpoint(dest));
+        if !ACTIVE.lock().unwrap().insert(key.clone()) {
+            return None;
+        }
+
+        Some(ActiveTransfer { key })
+    }
+}
+
+impl Drop for ActiveTransfer {
+    fn drop(&mut self) {
+        ACTIVE.lock().unwrap().remove(&self.key);
+    }
+}
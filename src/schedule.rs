@@ -0,0 +1,220 @@
+use chrono::{DateTime, Days, Duration, LocalResult, NaiveDate, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Schedule {
+    /// Every `minutes` after the previous run.
+    Interval { minutes: u32 },
+    /// Every day at the given local time.
+    Daily { hour: u32, minute: u32 },
+}
+
+impl Schedule {
+    pub fn label(&self) -> String {
+        match self {
+            Schedule::Interval { minutes } => format!("every {} min", minutes),
+            Schedule::Daily { hour, minute } => format!("daily at {:02}:{:02}", hour, minute),
+        }
+    }
+
+    /// When the next run is due. `anchor` is the last run (or when the
+    /// schedule was set up); intervals count from it, daily runs take the
+    /// first occurrence after it.
+    pub fn next_run<Tz: TimeZone>(&self, anchor: DateTime<Tz>) -> DateTime<Tz> {
+        match *self {
+            // Plain duration arithmetic, so DST changes don't shift intervals.
+            Schedule::Interval { minutes } => anchor + Duration::minutes(minutes.max(1) as i64),
+            Schedule::Daily { hour, minute } => {
+                let time =
+                    NaiveTime::from_hms_opt(hour.min(23), minute.min(59), 0).unwrap_or_default();
+                let zone = anchor.timezone();
+                let mut date = anchor.date_naive();
+                loop {
+                    let candidate = local_time_on(&zone, date, time);
+                    if candidate > anchor {
+                        return candidate;
+                    }
+                    date = date + Days::new(1);
+                }
+            }
+        }
+    }
+}
+
+/// Scheduled runs that came due together and ran one after another, from
+/// the first one starting until none are left due.
+#[derive(Default)]
+pub struct ScheduledBatch {
+    pub succeeded: Vec<String>,
+    pub failed: Vec<String>,
+    /// Due profiles passed over once the batch stopped.
+    pub skipped: Vec<String>,
+    /// Set by the first failure when the batch stops on one.
+    pub stopped: bool,
+}
+
+impl ScheduledBatch {
+    pub fn record(&mut self, name: &str, succeeded: bool, stop_on_failure: bool) {
+        if succeeded {
+            self.succeeded.push(name.to_string());
+        } else {
+            self.failed.push(name.to_string());
+            self.stopped |= stop_on_failure;
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "Scheduled runs finished: {} succeeded, {} failed",
+            self.succeeded.len(),
+            self.failed.len()
+        );
+        if !self.failed.is_empty() {
+            summary.push_str(&format!(" ({})", self.failed.join(", ")));
+        }
+        if self.stopped
+            && let Some(failed) = self.failed.first()
+        {
+            summary.push_str(&format!("\nStopped after {} failed", failed));
+            if !self.skipped.is_empty() {
+                summary.push_str(&format!("; skipped {}", self.skipped.join(", ")));
+            }
+        }
+        summary
+    }
+}
+
+/// `time` on `date` in `zone`. Ambiguous times (clocks going back)
+/// take the first occurrence, and times skipped by clocks going forward run
+/// at the first valid minute after the gap.
+fn local_time_on<Tz: TimeZone>(zone: &Tz, date: NaiveDate, time: NaiveTime) -> DateTime<Tz> {
+    let mut naive = date.and_time(time);
+    loop {
+        match zone.from_local_datetime(&naive) {
+            LocalResult::Single(x) => return x,
+            LocalResult::Ambiguous(earliest, _) => return earliest,
+            LocalResult::None => naive += Duration::minutes(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, MappedLocalTime, NaiveDateTime};
+
+    /// Central European time for 2024: +01:00, and +02:00 from 31 March
+    /// 01:00 UTC until 27 October 01:00 UTC.
+    #[derive(Clone)]
+    struct Cet;
+
+    const WINTER: i32 = 3600;
+    const SUMMER: i32 = 7200;
+
+    fn naive(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
+    }
+
+    impl TimeZone for Cet {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Cet {
+            Cet
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> MappedLocalTime<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_local_datetime(
+            &self,
+            local: &NaiveDateTime,
+        ) -> MappedLocalTime<FixedOffset> {
+            // Every offset that maps `local` back to itself; the summer one comes first in UTC.
+            let offsets = [SUMMER, WINTER]
+                .into_iter()
+                .map(|x| FixedOffset::east_opt(x).unwrap())
+                .filter(|x| self.offset_from_utc_datetime(&(*local - *x)) == *x)
+                .collect::<Vec<_>>();
+            match offsets[..] {
+                [] => MappedLocalTime::None,
+                [x] => MappedLocalTime::Single(x),
+                [a, b, ..] => MappedLocalTime::Ambiguous(a, b),
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_utc_datetime(&self, at: &NaiveDateTime) -> FixedOffset {
+            let summer = *at >= naive(2024, 3, 31, 1, 0) && *at < naive(2024, 10, 27, 1, 0);
+            FixedOffset::east_opt(if summer { SUMMER } else { WINTER }).unwrap()
+        }
+    }
+
+    fn local<Tz: TimeZone>(zone: &Tz, y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Tz> {
+        zone.from_local_datetime(&naive(y, m, d, h, min))
+            .earliest()
+            .unwrap()
+    }
+
+    #[test]
+    fn interval_crosses_midnight() {
+        let zone = FixedOffset::east_opt(WINTER).unwrap();
+        let next = Schedule::Interval { minutes: 30 }.next_run(local(&zone, 2024, 1, 10, 23, 50));
+        assert_eq!(next, local(&zone, 2024, 1, 11, 0, 20));
+    }
+
+    #[test]
+    fn daily_time_already_passed_today() {
+        let zone = FixedOffset::east_opt(WINTER).unwrap();
+        let daily = Schedule::Daily { hour: 3, minute: 0 };
+        assert_eq!(
+            daily.next_run(local(&zone, 2024, 1, 10, 10, 0)),
+            local(&zone, 2024, 1, 11, 3, 0)
+        );
+        // Due at exactly the anchor means it just ran.
+        assert_eq!(
+            daily.next_run(local(&zone, 2024, 1, 10, 3, 0)),
+            local(&zone, 2024, 1, 11, 3, 0)
+        );
+        assert_eq!(
+            daily.next_run(local(&zone, 2024, 1, 10, 2, 59)),
+            local(&zone, 2024, 1, 10, 3, 0)
+        );
+    }
+
+    #[test]
+    fn daily_in_spring_forward_gap_runs_after_it() {
+        let next = Schedule::Daily {
+            hour: 2,
+            minute: 30,
+        }
+        .next_run(local(&Cet, 2024, 3, 30, 12, 0));
+        assert_eq!(next, local(&Cet, 2024, 3, 31, 3, 0));
+        assert_eq!(next.naive_utc(), naive(2024, 3, 31, 1, 0));
+    }
+
+    #[test]
+    fn daily_in_fall_back_overlap_takes_first_occurrence() {
+        let next = Schedule::Daily {
+            hour: 2,
+            minute: 30,
+        }
+        .next_run(local(&Cet, 2024, 10, 26, 12, 0));
+        assert_eq!(next.naive_utc(), naive(2024, 10, 27, 0, 30));
+        assert_eq!(next.offset().local_minus_utc(), SUMMER);
+    }
+
+    #[test]
+    fn interval_keeps_its_length_across_dst() {
+        let anchor = local(&Cet, 2024, 3, 31, 1, 30);
+        let next = Schedule::Interval { minutes: 60 }.next_run(anchor);
+        assert_eq!(next - anchor, Duration::minutes(60));
+        assert_eq!(next.naive_local(), naive(2024, 3, 31, 3, 30));
+    }
+}
@@ -0,0 +1,278 @@
+use crate::help_text::flag_help;
+use crate::options::{EnvOverride, RsyncOptions, is_secret};
+use crate::utils::shell_quote;
+use chrono::Local;
+use std::ffi::OsStr;
+use std::process::Command;
+
+/// Stand-in path for a list file the script writes itself. The command is
+/// built with it in place of a real path and it is swapped for `"$name"`.
+pub struct ScriptList<'a> {
+    pub placeholder: &'a str,
+    pub name: &'a str,
+    pub content: String,
+}
+
+const HEREDOC_END: &str = "R_SYNCED_EOF";
+
+/// A heredoc delimiter that isn't a line of `content`, which would end the
+/// list early and run the rest of it as commands.
+fn heredoc_end(content: &str) -> String {
+    let mut end = HEREDOC_END.to_string();
+    let mut n = 1;
+    while content.lines().any(|x| x == end) {
+        end = format!("{}_{}", HEREDOC_END, n);
+        n += 1;
+    }
+    end
+}
+
+fn script_arg(arg: &OsStr, lists: &[ScriptList]) -> String {
+    let arg = arg.to_string_lossy();
+    for list in lists {
+        if let Some(flag) = arg.strip_suffix(list.placeholder) {
+            return format!("{}\"${}\"", shell_quote(flag), list.name);
+        }
+    }
+
+    shell_quote(&arg)
+}
+
+/// A bash script running `cmd`, with a comment for every flag it uses and a
+/// commented-out dry-run of the same command. Secret environment variables
+/// are left for whoever runs the script to set.
+pub fn render(cmd: &Command, options: &RsyncOptions, lists: &[ScriptList]) -> String {
+    let mut script = String::from("#!/usr/bin/env bash\n");
+    script.push_str(&format!(
+        "# Exported from r-synced on {}.\nset -euo pipefail\n\n",
+        Local::now().format("%Y-%m-%d %H:%M")
+    ));
+
+    // Skip the ionice arguments in front of the rsync binary.
+    let rsync_args = match cmd.get_program() == options.rsync_binary() {
+        true => 0,
+        false => cmd
+            .get_args()
+            .position(|x| x == options.rsync_binary())
+            .map_or(0, |x| x + 1),
+    };
+
+    let mut described = Vec::new();
+    for arg in cmd.get_args().skip(rsync_args) {
+        let arg = arg.to_string_lossy();
+        let flag = arg.split('=').next().unwrap_or_default().to_string();
+        if !arg.starts_with('-') || described.contains(&flag) {
+            continue;
+        }
+        if let Some(help) = flag_help(&flag) {
+            described.push(flag);
+            if described.len() == 1 {
+                script.push_str("# Options:\n");
+            }
+            script.push_str(&format!("#   {:<24} {}\n", described.last().unwrap(), help));
+        }
+    }
+    if !described.is_empty() {
+        script.push('\n');
+    }
+
+    for (key, env) in &options.env_overrides {
+        match env {
+            EnvOverride::Unset => script.push_str(&format!("unset {}\n", key)),
+            EnvOverride::Set(value) if is_secret(key, value) => script.push_str(&format!(
+                "export {key}=\"${{{key}:?set {key} before running this script}}\"\n"
+            )),
+            EnvOverride::Set(value) => {
+                script.push_str(&format!("export {}={}\n", key, shell_quote(value)))
+            }
+        }
+    }
+    for var in &options.env_vars {
+        let key = var.key.trim();
+        if key.is_empty() {
+            continue;
+        }
+        if var.is_secret() {
+            script.push_str(&format!(
+                "export {key}=\"${{{key}:?set {key} before running this script}}\"\n"
+            ));
+        } else {
+            script.push_str(&format!("export {}={}\n", key, shell_quote(&var.value)));
+        }
+    }
+    if !options.env_overrides.is_empty()
+        || options.env_vars.iter().any(|x| !x.key.trim().is_empty())
+    {
+        script.push('\n');
+    }
+
+    for list in lists {
+        script.push_str(&format!("{}=\"$(mktemp)\"\n", list.name));
+    }
+    if !lists.is_empty() {
+        let names: Vec<_> = lists.iter().map(|x| format!("\"${}\"", x.name)).collect();
+        script.push_str(&format!("trap 'rm -f {}' EXIT\n", names.join(" ")));
+        for list in lists {
+            let end = heredoc_end(&list.content);
+            script.push_str(&format!("cat > \"${}\" <<'{}'\n", list.name, end));
+            script.push_str(&list.content);
+            if !list.content.ends_with('\n') {
+                script.push('\n');
+            }
+            script.push_str(&end);
+            script.push('\n');
+        }
+        script.push('\n');
+    }
+
+    let mut program = Vec::new();
+    if options.low_priority {
+        program.push(format!("nice -n {}", options.nice_level));
+    }
+    program.push(shell_quote(&cmd.get_program().to_string_lossy()));
+    let program = program.join(" ");
+    let args: Vec<_> = cmd.get_args().map(|x| script_arg(x, lists)).collect();
+
+    // Source and destination always come last.
+    let (flags, paths) = args.split_at(args.len().saturating_sub(2));
+    script.push_str("# To only see what would change, run this instead:\n");
+    script.push_str(&format!(
+        "# {} {} --dry-run {}\n\n",
+        program,
+        flags.join(" "),
+        paths.join(" ")
+    ));
+
+    script.push_str(&program);
+    for arg in &args {
+        script.push_str(" \\\n    ");
+        script.push_str(arg);
+    }
+    script.push('\n');
+
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::EnvVar;
+
+    #[test]
+    fn renders_lists_secrets_and_dry_run() {
+        let mut options = RsyncOptions {
+            src: "/home/me/My Photos/".to_string(),
+            dest: "backup:photos".to_string(),
+            ..Default::default()
+        };
+        options.env_vars.push(EnvVar {
+            key: "RSYNC_PASSWORD".to_string(),
+            value: "hunter2".to_string(),
+        });
+        options.env_vars.push(EnvVar {
+            key: "API_TOKEN".to_string(),
+            value: "abc123".to_string(),
+        });
+        options.env_vars.push(EnvVar {
+            key: "LANG".to_string(),
+            value: "C".to_string(),
+        });
+
+        let mut cmd = Command::new("rsync");
+        cmd.args([
+            "-a",
+            "--exclude-from=@@exclude_from@@",
+            "--files-from=@@files_from@@",
+            &options.src,
+            &options.dest,
+        ]);
+        let lists = [
+            ScriptList {
+                placeholder: "@@exclude_from@@",
+                name: "exclude_from",
+                content: "*.tmp\n".to_string(),
+            },
+            ScriptList {
+                placeholder: "@@files_from@@",
+                name: "files_from",
+                content: "a.jpg\nb.jpg".to_string(),
+            },
+        ];
+        let script = render(&cmd, &options, &lists);
+
+        // Every placeholder turns into its variable, filled through a heredoc.
+        assert!(!script.contains("@@"));
+        assert!(script.contains("--exclude-from=\"$exclude_from\""));
+        assert!(script.contains("--files-from=\"$files_from\""));
+        assert!(script.contains("exclude_from=\"$(mktemp)\"\n"));
+        assert!(script.contains("cat > \"$exclude_from\" <<'R_SYNCED_EOF'\n*.tmp\nR_SYNCED_EOF\n"));
+        assert!(
+            script.contains("cat > \"$files_from\" <<'R_SYNCED_EOF'\na.jpg\nb.jpg\nR_SYNCED_EOF\n")
+        );
+
+        // Secrets are required from the environment instead of written out.
+        assert!(script.contains("export RSYNC_PASSWORD=\"${RSYNC_PASSWORD:?set RSYNC_PASSWORD before running this script}\"\n"));
+        assert!(script.contains(
+            "export API_TOKEN=\"${API_TOKEN:?set API_TOKEN before running this script}\"\n"
+        ));
+        assert!(!script.contains("hunter2"));
+        assert!(!script.contains("abc123"));
+        assert!(script.contains("export LANG=C\n"));
+
+        let dry_run = script.lines().find(|x| x.contains("--dry-run")).unwrap();
+        assert!(dry_run.starts_with("# rsync -a "));
+        assert!(dry_run.ends_with("--dry-run '/home/me/My Photos/' backup:photos"));
+    }
+
+    /// Runs the script with `sh -c` standing in for rsync, printing the list
+    /// file and the paths it got, so quoting mistakes show up as wrong output.
+    #[test]
+    fn quotes_what_the_shell_would_expand() {
+        let options = RsyncOptions {
+            src: "/tmp/it's $HOME `id`/".to_string(),
+            dest: "backup:dir with $PATH".to_string(),
+            ..Default::default()
+        };
+        let mut cmd = Command::new("sh");
+        cmd.args([
+            "-c",
+            r#"cat "${1#--files-from=}" && printf '%s\n' "$2" "$3""#,
+            "sh",
+            "--files-from=@@files_from@@",
+            &options.src,
+            &options.dest,
+        ]);
+        let content = "it's\n$HOME/`id`\nR_SYNCED_EOF\nlast\n";
+        let lists = [ScriptList {
+            placeholder: "@@files_from@@",
+            name: "files_from",
+            content: content.to_string(),
+        }];
+        let script = render(&cmd, &options, &lists);
+        assert!(script.contains("<<'R_SYNCED_EOF_1'\n"));
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, script.as_bytes()).unwrap();
+        let check = Command::new("bash")
+            .arg("-n")
+            .arg(file.path())
+            .output()
+            .unwrap();
+        assert!(
+            check.status.success(),
+            "{}",
+            String::from_utf8_lossy(&check.stderr)
+        );
+
+        let output = Command::new("bash").arg(file.path()).output().unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            format!("{}{}\n{}\n", content, options.src, options.dest)
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use crate::utils::{format_bytes, format_count};
+use eframe::egui;
+use nix::sys::signal::{self, Signal};
+use nix::unistd::Pid;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Rough size of the source, shown next to the Run button.
+pub struct SourceEstimate {
+    pub files: u64,
+    pub bytes: u64,
+    /// Counted by a dry-run, so excludes and filters are taken into account.
+    pub filtered: bool,
+}
+
+impl SourceEstimate {
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        let text = format!(
+            "≈ {} files, {}",
+            format_count(self.files),
+            format_bytes(self.bytes)
+        );
+        let hover = if self.filtered {
+            "Estimate from a dry-run of the current settings"
+        } else {
+            "Estimate from walking the source; excludes and filters are not taken into account"
+        };
+        ui.weak(text).on_hover_text(hover);
+    }
+}
+
+/// A size estimate running on its own thread. Dropping it stops the scan.
+pub struct SourceScan {
+    cancel: Arc<AtomicBool>,
+    /// The dry-run doing the counting, if it is one.
+    pid: Option<u32>,
+    rx: Receiver<Option<SourceEstimate>>,
+}
+
+impl SourceScan {
+    /// Walks a local source directory.
+    pub fn walk(src: &str, ctx: &egui::Context) -> SourceScan {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        let root = PathBuf::from(src);
+        let walk_cancel = cancel.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(walk(&root, &walk_cancel));
+            ctx.request_repaint();
+        });
+
+        SourceScan {
+            cancel,
+            pid: None,
+            rx,
+        }
+    }
+
+    /// Waits for a dry-run and reads the estimate from its output.
+    pub fn dry_run(
+        child: Child,
+        parse: impl FnOnce(&str) -> Option<SourceEstimate> + Send + 'static,
+        ctx: &egui::Context,
+    ) -> SourceScan {
+        let pid = child.id();
+        let (tx, rx) = mpsc::channel();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let estimate = child
+                .wait_with_output()
+                .ok()
+                .filter(|x| x.status.success())
+                .and_then(|x| parse(&String::from_utf8_lossy(&x.stdout)));
+            let _ = tx.send(estimate);
+            ctx.request_repaint();
+        });
+
+        SourceScan {
+            cancel: Arc::new(AtomicBool::new(false)),
+            pid: Some(pid),
+            rx,
+        }
+    }
+
+    /// The outcome once the scan is done; None inside means it failed.
+    pub fn poll(&mut self) -> Option<Option<SourceEstimate>> {
+        let result = self.rx.try_recv().ok()?;
+        // The process is gone, and its PID may be reused.
+        self.pid = None;
+        Some(result)
+    }
+}
+
+impl Drop for SourceScan {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        if let Some(pid) = self.pid {
+            let _ = signal::kill(Pid::from_raw(pid as i32), Signal::SIGTERM);
+        }
+    }
+}
+
+/// Counts the files under `root` and adds up their sizes. Links are counted
+/// but not followed, same as rsync does with -l.
+fn walk(root: &Path, cancel: &AtomicBool) -> Option<SourceEstimate> {
+    let mut estimate = SourceEstimate {
+        files: 0,
+        bytes: 0,
+        filtered: false,
+    };
+
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        // The source itself is followed, like rsync does with a trailing slash.
+        let metadata = if path == root {
+            fs::metadata(&path)
+        } else {
+            fs::symlink_metadata(&path)
+        };
+        let Ok(metadata) = metadata else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if let Ok(entries) = fs::read_dir(&path) {
+                pending.extend(entries.filter_map(Result::ok).map(|x| x.path()));
+            }
+        } else {
+            estimate.files += 1;
+            if metadata.is_file() {
+                estimate.bytes += metadata.len();
+            }
+        }
+    }
+
+    Some(estimate)
+}
@@ -0,0 +1,227 @@
+use crate::history::HistoryEntry;
+use crate::utils::parse_human_number;
+use anyhow::Context;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum StatsFormat {
+    Json,
+    Csv,
+}
+
+impl StatsFormat {
+    pub fn label(&self) -> &'static str {
+        match self {
+            StatsFormat::Json => "JSON",
+            StatsFormat::Csv => "CSV (append a row)",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            StatsFormat::Json => "json",
+            StatsFormat::Csv => "csv",
+        }
+    }
+}
+
+/// The --stats values rsync 3.x prints, in its order, under the keys
+/// `parse_rsync_stats` stores them with. CSV rows always have these columns
+/// so rows appended by later runs line up.
+const CSV_STATS: [&str; 16] = [
+    "Number of files (total)",
+    "Number of files (regular)",
+    "Number of files (directories)",
+    "Number of files (links)",
+    "Number of created files",
+    "Number of deleted files",
+    "Number of regular files transferred",
+    "Total file size",
+    "Total transferred file size",
+    "Literal data",
+    "Matched data",
+    "File list size",
+    "File list generation time",
+    "File list transfer time",
+    "Total bytes sent",
+    "Total bytes received",
+];
+
+/// The label in snake case, e.g. `number_of_files_total` for "Number of files (total)".
+fn key(label: &str) -> String {
+    label
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// The leading number of a stats value, e.g. 1234 for "1,234 (reg: 1,000,
+/// dir: 234)" or 0.001 for "0.001 seconds". Anything else is kept as text.
+fn normalize(value: &str) -> Value {
+    let mut words = value.split_whitespace();
+    let Some(first) = words.next() else {
+        return Value::from(value);
+    };
+
+    if words.next() == Some("seconds")
+        && let Ok(secs) = first.replace(',', ".").parse::<f64>()
+    {
+        return Value::from(secs);
+    }
+    match parse_human_number(first) {
+        Some(x) => Value::from(x),
+        None => Value::from(value),
+    }
+}
+
+fn record(entry: &HistoryEntry, stats: &HashMap<String, String>) -> Map<String, Value> {
+    let mut record = Map::new();
+    record.insert(
+        "timestamp".to_string(),
+        Value::from(entry.started_at.to_rfc3339()),
+    );
+    record.insert("src".to_string(), Value::from(entry.options.src.clone()));
+    record.insert("dest".to_string(), Value::from(entry.options.dest.clone()));
+    record.insert(
+        "duration_secs".to_string(),
+        Value::from(entry.duration_secs),
+    );
+    record.insert(
+        "exit_code".to_string(),
+        entry.exit_code.map_or(Value::Null, Value::from),
+    );
+    for (label, value) in stats {
+        record.insert(key(label), normalize(value));
+    }
+    record
+}
+
+fn csv_field(value: &Value) -> String {
+    let text = match value {
+        Value::Null => String::new(),
+        Value::String(x) => x.clone(),
+        x => x.to_string(),
+    };
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+/// Writes the stats of `entry`'s run to `path`: a JSON object replacing the
+/// file, or a CSV row appended to it, with a header if the file is new.
+pub fn export(
+    path: &Path,
+    format: StatsFormat,
+    entry: &HistoryEntry,
+    stats: &HashMap<String, String>,
+) -> anyhow::Result<()> {
+    let record = record(entry, stats);
+
+    match format {
+        StatsFormat::Json => {
+            let data = serde_json::to_string_pretty(&record)?;
+            fs::write(path, data).with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        StatsFormat::Csv => {
+            let columns = ["timestamp", "src", "dest", "duration_secs", "exit_code"]
+                .into_iter()
+                .map(str::to_string)
+                .chain(CSV_STATS.iter().map(|x| key(x)))
+                .collect::<Vec<_>>();
+
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            let mut data = String::new();
+            if file.metadata().map(|x| x.len() == 0).unwrap_or(true) {
+                data.push_str(&columns.join(","));
+                data.push('\n');
+            }
+            let row = columns
+                .iter()
+                .map(|x| csv_field(record.get(x).unwrap_or(&Value::Null)))
+                .collect::<Vec<_>>();
+            data.push_str(&row.join(","));
+            data.push('\n');
+            file.write_all(data.as_bytes())
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_every_column_parse_rsync_stats_fills() {
+        let output = "\
+Number of files: 1,234 (reg: 1,000, dir: 230, link: 4)
+Number of created files: 3 (reg: 3)
+Number of deleted files: 0
+Number of regular files transferred: 3
+Total file size: 1,234,567 bytes
+Total transferred file size: 52,340 bytes
+Literal data: 52,340 bytes
+Matched data: 0 bytes
+File list size: 0
+File list generation time: 0.001 seconds
+File list transfer time: 0.000 seconds
+Total bytes sent: 53,012
+Total bytes received: 76
+";
+        let stats = crate::parse_rsync_stats(output);
+        for label in CSV_STATS {
+            assert!(stats.contains_key(label), "no value for {}", label);
+        }
+
+        assert_eq!(key("Number of files (total)"), "number_of_files_total");
+        assert_eq!(
+            key("Number of files (directories)"),
+            "number_of_files_directories"
+        );
+        assert_eq!(key("Total bytes sent"), "total_bytes_sent");
+        assert_eq!(
+            normalize(&stats["Number of files (regular)"]),
+            Value::from(1000)
+        );
+    }
+
+    #[test]
+    fn keeps_the_leading_number() {
+        assert_eq!(normalize("1,234 (reg: 1,000, dir: 234)"), Value::from(1234));
+        assert_eq!(normalize("1,234,567 bytes"), Value::from(1_234_567));
+        assert_eq!(normalize("1.23M bytes"), Value::from(1_230_000));
+        assert_eq!(normalize("0.001 seconds"), Value::from(0.001));
+        assert_eq!(normalize("0,001 seconds"), Value::from(0.001));
+        assert_eq!(normalize("0"), Value::from(0));
+        assert_eq!(normalize("DRY RUN"), Value::from("DRY RUN"));
+        assert_eq!(normalize(""), Value::from(""));
+    }
+
+    #[test]
+    fn quotes_csv_fields_only_when_needed() {
+        assert_eq!(csv_field(&Value::Null), "");
+        assert_eq!(csv_field(&Value::from(1234)), "1234");
+        assert_eq!(csv_field(&Value::from(0.001)), "0.001");
+        assert_eq!(
+            csv_field(&Value::from("/home/me/photos/")),
+            "/home/me/photos/"
+        );
+        assert_eq!(csv_field(&Value::from("a,b")), "\"a,b\"");
+        assert_eq!(csv_field(&Value::from("say \"hi\"")), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field(&Value::from("two\nlines")), "\"two\nlines\"");
+    }
+}
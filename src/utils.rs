@@ -1,5 +1,12 @@
 use lazy_static::lazy_static;
 use regex::Regex;
+use std::collections::VecDeque;
+use std::collections::hash_map::RandomState;
+use std::env;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, PartialEq)]
 pub struct RsyncProgress {
@@ -7,27 +14,64 @@ pub struct RsyncProgress {
     pub percentage: u8,
     pub speed: String,
     pub estimated_time: String,
+    /// Set from the `(xfr#N, to-chk=R/T)` suffix rsync adds once a file is done.
+    pub xfr_number: Option<u64>,
+    pub check_remaining: Option<u64>,
+    pub check_total: Option<u64>,
+    /// `ir-chk` rather than `to-chk`: incremental recursion is still scanning,
+    /// so `check_total` keeps growing.
+    pub incremental: bool,
+}
+
+impl RsyncProgress {
+    /// Fraction of the files rsync has checked so far, if it reported it.
+    pub fn checked_fraction(&self) -> Option<f32> {
+        let total = self.check_total.filter(|&x| x > 0)?;
+        let remaining = self.check_remaining?.min(total);
+        Some((total - remaining) as f32 / total as f32)
+    }
+
+    /// Files checked so far and the total to check, for "Checking: N of M".
+    pub fn checked(&self) -> Option<(u64, u64)> {
+        let total = self.check_total?;
+        Some((total - self.check_remaining?.min(total), total))
+    }
 }
 
 pub fn parse_rsync_progress(line: &str) -> Option<RsyncProgress> {
     lazy_static! {
+        // The ETA is missing or `??:??:??` on some final lines, and `(xfr#…)` may follow.
         static ref RE: Regex = Regex::new(
-            r"^([\d.]+)\s+(\d+)%\s+([\d,]+\w+/\w+)\s+(\d{1,2}:\d{2}:\d{2})"
+            r"^([\d.,]+[KMGTP]?)\s+(\d{1,3})%\s+([\d.,]+\w+/\w+)(?:\s+(\d+:\d{2}:\d{2}|\?\?:\?\?:\?\?))?(?:\s|$)"
         ).unwrap();
+        // rsync before 3.1 spells it `(xfer#1, to-check=3/100)`.
+        static ref RE_CHECK: Regex =
+            Regex::new(r"\(xfe?r#(\d+),\s*(ir|to)-ch(?:ec)?k=(\d+)/(\d+)\)").unwrap();
     }
 
     let caps = RE.captures(line.trim())?;
-    let bytes_str = caps.get(1)?.as_str().replace('.', "");
-    let bytes_transferred = bytes_str.parse::<u64>().ok()?;
-    let percentage = caps.get(2)?.as_str().parse::<u8>().ok()?;
+    // With -h the byte count carries a unit suffix, e.g. `1.23M`.
+    let bytes_transferred = parse_human_number(caps.get(1)?.as_str())?;
+    let percentage = caps.get(2)?.as_str().parse::<u32>().ok()?.min(100) as u8;
     let speed = caps.get(3)?.as_str().to_string();
-    let estimated_time = caps.get(4)?.as_str().to_string();
+    let estimated_time = caps
+        .get(4)
+        .map(|x| x.as_str())
+        .unwrap_or("--:--:--")
+        .to_string();
+
+    let check = RE_CHECK.captures(line);
+    let check_number = |i| check.as_ref()?.get(i)?.as_str().parse::<u64>().ok();
 
     Some(RsyncProgress {
         bytes_transferred,
         percentage,
         speed,
         estimated_time,
+        xfr_number: check_number(1),
+        check_remaining: check_number(3),
+        check_total: check_number(4),
+        incremental: check.as_ref().is_some_and(|x| &x[2] == "ir"),
     })
 }
 
@@ -45,4 +89,810 @@ pub fn format_bytes(bytes: u64) -> String {
     }
 
     format!("{:.1} {}", size, UNITS[i])
-}
\ No newline at end of file
+}
+
+/// Formats a count with thousands separators, e.g. `1,234`.
+pub fn format_count(n: u64) -> String {
+    let digits = n.to_string();
+    let mut result = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            result.push(',');
+        }
+        result.push(c);
+    }
+    result
+}
+
+pub fn format_duration(secs: u64) -> String {
+    let hours = secs / 3600;
+    let minutes = (secs % 3600) / 60;
+    let seconds = secs % 60;
+
+    if hours > 0 {
+        format!("{}h {}m {}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// A duration rounded to what an estimate can promise, e.g. "~18 minutes".
+pub fn format_rough_duration(secs: u64) -> String {
+    let minutes = (secs + 30) / 60;
+    match minutes {
+        0 => "under a minute".to_string(),
+        1 => "~1 minute".to_string(),
+        2..60 => format!("~{} minutes", minutes),
+        _ => format!("~{}h {}m", minutes / 60, minutes % 60),
+    }
+}
+
+/// Parses a number as printed by rsync. Digit-group separators are dropped, and
+/// the unit suffixes added by `--human-readable` (`1.23K`, `4.56M`, ...) are
+/// expanded to the raw value, using the 1000-based units rsync prints with `-h`.
+pub fn parse_human_number(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let multiplier = match s.chars().last()? {
+        'K' | 'k' => 1e3,
+        'M' | 'm' => 1e6,
+        'G' | 'g' => 1e9,
+        'T' | 't' => 1e12,
+        'P' | 'p' => 1e15,
+        _ => return parse_rsync_number(s),
+    };
+
+    // rsync uses either a dot or a comma as the decimal point depending on the locale.
+    let mantissa = s[..s.len() - 1].replace(',', ".").parse::<f64>().ok()?;
+    Some((mantissa * multiplier).round() as u64)
+}
+
+/// Parses a transfer rate from the progress line, e.g. `12.50MB/s`,
+/// `1,234.56kB/s`, `0.00kB/s` or `1,024kB/s`, into bytes per second. rsync's
+/// progress rates are 1024-based and have two decimals, so a last separator
+/// followed by three digits groups thousands and any other is the decimal point.
+pub fn parse_speed_bps(s: &str) -> Option<f64> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^([\d.,]+)\s*([kKMGT]?)B/s$").unwrap();
+    }
+
+    let caps = RE.captures(s.trim())?;
+    let number = &caps[1];
+    let value = match number.rfind([',', '.']) {
+        Some(i) if number.len() - i - 1 != 3 => {
+            format!(
+                "{}.{}",
+                number[..i].replace([',', '.'], ""),
+                &number[i + 1..]
+            )
+        }
+        _ => number.replace([',', '.'], ""),
+    };
+
+    let multiplier = match &caps[2] {
+        "k" | "K" => 1024.0,
+        "M" => 1024.0 * 1024.0,
+        "G" => 1024.0 * 1024.0 * 1024.0,
+        "T" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => 1.0,
+    };
+
+    Some(value.parse::<f64>().ok()? * multiplier)
+}
+
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    let paths = env::var_os("PATH")?;
+    env::split_paths(&paths)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Splits an rsync endpoint into host and path when it refers to a remote
+/// location (`host:path`, `user@host:path`, `host::module` or `rsync://host/path`).
+/// A colon after the first slash is part of a local path, same as rsync treats it.
+pub fn split_remote(endpoint: &str) -> Option<(&str, &str)> {
+    if let Some(rest) = endpoint.strip_prefix("rsync://") {
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+        return Some((host, path));
+    }
+
+    let colon = endpoint.find(':')?;
+    if endpoint.find('/').is_some_and(|slash| slash < colon) {
+        return None;
+    }
+
+    let host = &endpoint[..colon];
+    let path = endpoint[colon + 1..].trim_start_matches(':');
+    let host = host.rsplit_once('@').map(|x| x.1).unwrap_or(host);
+    if host.is_empty() {
+        return None;
+    }
+
+    Some((host, path))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemAction {
+    New,
+    Updated,
+    Deleted,
+    Directory,
+    Other,
+}
+
+impl ItemAction {
+    pub fn badge(&self) -> &'static str {
+        match self {
+            ItemAction::New => "NEW",
+            ItemAction::Updated => "UPD",
+            ItemAction::Deleted => "DEL",
+            ItemAction::Directory => "DIR",
+            ItemAction::Other => "ATTR",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemizedLine {
+    pub action: ItemAction,
+    pub size: u64,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// How bad a line rsync printed to stderr is. Files that vanished or were
+/// skipped don't stop the transfer; anything else is treated as an error.
+pub fn stderr_severity(line: &str) -> Severity {
+    let line = line.to_lowercase();
+    if line.contains("warning")
+        || line.contains("file has vanished")
+        || line.starts_with("skipping ")
+    {
+        Severity::Warning
+    } else {
+        Severity::Error
+    }
+}
+
+const REMOTE_RSYNC_MISSING: &str = "rsync doesn't seem to be installed on the remote host. \
+     Install it there (e.g. `sudo apt install rsync`), or if it lives outside the remote PATH, \
+     set Remote rsync path (--rsync-path) under Advanced.";
+
+/// stderr patterns whose plain meaning isn't obvious from rsync's message,
+/// with what to do about them.
+const KNOWN_ERRORS: [(&str, &str); 4] = [
+    ("rsync: command not found", REMOTE_RSYNC_MISSING),
+    // dash and busybox sh word it differently from bash.
+    ("rsync: not found", REMOTE_RSYNC_MISSING),
+    (
+        "connection unexpectedly closed (0 bytes received so far)",
+        "The remote side hung up before rsync started. Usually rsync is missing on the remote host \
+         or a shell startup file there prints output; check that `ssh <host> rsync --version` works.",
+    ),
+    (
+        "protocol version mismatch",
+        "The remote login prints text that confuses rsync. Make the remote shell startup files quiet for non-interactive logins.",
+    ),
+];
+
+/// An explanation for the first known problem in `stderr`.
+pub fn known_error_hint(stderr: &str) -> Option<&'static str> {
+    KNOWN_ERRORS
+        .iter()
+        .find(|(pattern, _)| stderr.contains(pattern))
+        .map(|(_, hint)| *hint)
+}
+
+/// What a dry-run would do, counted from its itemized lines.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DryRunSummary {
+    pub new: u64,
+    pub modified: u64,
+    pub deleted: u64,
+}
+
+impl DryRunSummary {
+    pub fn from_items<'a>(items: impl IntoIterator<Item = &'a ItemizedLine>) -> DryRunSummary {
+        let mut summary = DryRunSummary::default();
+        for item in items {
+            match item.action {
+                ItemAction::New => summary.new += 1,
+                ItemAction::Updated => summary.modified += 1,
+                ItemAction::Deleted => summary.deleted += 1,
+                ItemAction::Directory | ItemAction::Other => {}
+            }
+        }
+        summary
+    }
+
+    pub fn text(&self) -> String {
+        format!(
+            "→ {} new  ↑ {} modified  ← {} deleted",
+            format_count(self.new),
+            format_count(self.modified),
+            format_count(self.deleted)
+        )
+    }
+}
+
+/// Parses a line printed with `--out-format=%i %l %n%L`, e.g.
+/// `>f+++++++++ 1024 photos/summer trip.jpg` or `*deleting   0 old/file.txt`.
+pub fn parse_itemized_line(line: &str) -> Option<ItemizedLine> {
+    lazy_static! {
+        static ref RE: Regex =
+            Regex::new(r"^(\*deleting|[<>ch.][fdLDS][^ ]{9})\s+(\d+) (.+)$").unwrap();
+    }
+
+    let caps = RE.captures(line)?;
+    let flags = caps.get(1)?.as_str();
+    let size = caps.get(2)?.as_str().parse::<u64>().ok()?;
+    let path = caps.get(3)?.as_str().to_string();
+
+    let action = if flags == "*deleting" {
+        ItemAction::Deleted
+    } else if flags.as_bytes()[1] == b'd' {
+        ItemAction::Directory
+    } else if flags[2..].starts_with('+') {
+        ItemAction::New
+    } else if flags.starts_with(['<', '>']) {
+        ItemAction::Updated
+    } else {
+        ItemAction::Other
+    };
+
+    Some(ItemizedLine { action, size, path })
+}
+
+/// Parses a plain number as printed by rsync, which groups digits with either
+/// commas or dots depending on the locale. Separators every three digits are
+/// treated as grouping, anything else as a decimal point (the fraction is
+/// truncated): `1,234` and `1.234` are 1234, `1,234.5` is 1234, `12.5` is 12.
+pub fn parse_rsync_number(s: &str) -> Option<u64> {
+    lazy_static! {
+        static ref RE_GROUPED: Regex = Regex::new(r"^\d{1,3}([.,]\d{3})+$").unwrap();
+    }
+
+    let s = s.trim();
+    if RE_GROUPED.is_match(s) {
+        return s.replace([',', '.'], "").parse::<u64>().ok();
+    }
+
+    // When both separators show up, the last one is the decimal point.
+    let integer = match s.rfind([',', '.']) {
+        Some(i) => &s[..i],
+        None => s,
+    };
+
+    integer.replace([',', '.'], "").parse::<u64>().ok()
+}
+
+/// Quotes `arg` for a POSIX shell, leaving it alone when that isn't needed.
+pub fn shell_quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_-./:@%+=,".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        return arg.to_string();
+    }
+
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+const COMPLETION_WINDOW: Duration = Duration::from_secs(5);
+
+/// Transfer rate over the last few seconds, worked out from the sizes of
+/// finished files. Small files finish before rsync prints any progress for
+/// them, so this is the only speed there is on trees of tiny files.
+#[derive(Default)]
+pub struct CompletionRate {
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl CompletionRate {
+    pub fn push(&mut self, at: Instant, bytes: u64) {
+        self.samples.push_back((at, bytes));
+        self.expire(at);
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while self
+            .samples
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > COMPLETION_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Bytes per second over the window. Spans under a second count as one
+    /// second, so the first few files don't suggest an absurd rate.
+    pub fn bps(&mut self, now: Instant) -> Option<f64> {
+        self.expire(now);
+        let (first, _) = self.samples.front()?;
+        let span = now.duration_since(*first).as_secs_f64().max(1.0);
+        Some(self.samples.iter().map(|(_, bytes)| *bytes).sum::<u64>() as f64 / span)
+    }
+}
+
+/// How long without a progress line from rsync before the speed is worked
+/// out from finished files instead.
+const NATIVE_PROGRESS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Bytes a run has transferred so far.
+#[derive(Default)]
+pub struct ByteCount {
+    /// Bytes of finished files plus the latest count of the current one; with
+    /// --info=progress2 the latter is already the running total.
+    done: u64,
+    file_bytes: u64,
+    /// Size of the file being transferred, counted as done once the next one starts.
+    file_size: u64,
+    completions: CompletionRate,
+    /// When rsync last printed a progress line with a speed.
+    native_progress_at: Option<Instant>,
+}
+
+impl ByteCount {
+    pub fn total(&self) -> u64 {
+        self.done + self.file_bytes
+    }
+
+    pub fn native_progress(&mut self, at: Instant) {
+        self.native_progress_at = Some(at);
+    }
+
+    /// The byte count of a progress line.
+    pub fn progress(&mut self, bytes_sent: u64) {
+        // Per-file byte counts start over with each file.
+        if bytes_sent < self.file_bytes {
+            self.done += self.file_bytes;
+        }
+        self.file_bytes = bytes_sent;
+    }
+
+    /// Counts the previous file as done. Returns the speed worked out from
+    /// finished files when rsync hasn't printed a progress line in a while.
+    pub fn next_file(&mut self, now: Instant, size: u64) -> Option<f64> {
+        let finished = self.file_size.max(self.file_bytes);
+        self.done += finished;
+        self.file_bytes = 0;
+        self.file_size = size;
+
+        self.completions.push(now, finished);
+        let quiet = self
+            .native_progress_at
+            .is_none_or(|at| now.duration_since(at) > NATIVE_PROGRESS_TIMEOUT);
+        if quiet {
+            self.completions.bps(now)
+        } else {
+            None
+        }
+    }
+
+    /// Counts the file being transferred as done.
+    pub fn finish(&mut self) {
+        self.done += self.file_size.max(self.file_bytes);
+        self.file_bytes = 0;
+        self.file_size = 0;
+    }
+}
+
+/// Seconds left for `remaining` bytes at the average observed speed, or at
+/// `cap_bps` when that is lower; the flag tells whether the cap was used.
+/// Without any speed samples yet only the cap can give an estimate.
+pub fn estimate_eta(
+    remaining: u64,
+    observed_bps: Option<f64>,
+    cap_bps: Option<f64>,
+) -> Option<(u64, bool)> {
+    let observed = observed_bps.filter(|x| *x > 0.0);
+    let cap = cap_bps.filter(|x| *x > 0.0);
+    let (rate, capped) = match (observed, cap) {
+        (Some(observed), Some(cap)) if cap < observed => (cap, true),
+        (Some(observed), _) => (observed, false),
+        (None, Some(cap)) => (cap, true),
+        (None, None) => return None,
+    };
+
+    Some(((remaining as f64 / rate).ceil() as u64, capped))
+}
+
+/// Six random hex digits telling concurrent or consecutive runs apart.
+pub fn new_run_id() -> String {
+    let random = RandomState::new().build_hasher().finish();
+    format!("{:06x}", random & 0xff_ffff)
+}
+
+/// Up to `n` of `items`, picked at random.
+pub fn random_sample<T: std::hash::Hash>(items: impl IntoIterator<Item = T>, n: usize) -> Vec<T> {
+    let state = RandomState::new();
+    let mut items: Vec<_> = items.into_iter().collect();
+    items.sort_by_cached_key(|x| state.hash_one(x));
+    items.truncate(n);
+    items
+}
+
+/// Last component of a local path or the path part of a remote endpoint.
+pub fn endpoint_name(endpoint: &str) -> &str {
+    let path = split_remote(endpoint).map_or(endpoint, |(_, path)| path);
+    path.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(path)
+}
+
+/// What an rsync exit code means, as listed in rsync(1).
+pub fn describe_exit_code(code: i32) -> &'static str {
+    match code {
+        0 => "Success",
+        1 => "Syntax or usage error",
+        2 => "Protocol incompatibility",
+        3 => "Errors selecting input/output files, dirs",
+        4 => "Requested action not supported",
+        5 => "Error starting client-server protocol",
+        6 => "Daemon unable to append to log-file",
+        10 => "Error in socket I/O",
+        11 => "Error in file I/O",
+        12 => "Error in rsync protocol data stream",
+        13 => "Errors with program diagnostics",
+        14 => "Error in IPC code",
+        20 => "Received SIGUSR1 or SIGINT",
+        21 => "Some error returned by waitpid()",
+        22 => "Error allocating core memory buffers",
+        23 => "Partial transfer due to error",
+        24 => "Partial transfer due to vanished source files",
+        25 => "The --max-delete limit stopped deletions",
+        30 => "Timeout in data send/receive",
+        35 => "Timeout waiting for daemon connection",
+        255 => "SSH connection failed",
+        -1 => "Killed by a signal",
+        _ => "Unknown error",
+    }
+}
+
+/// Flags this app may emit that older rsync releases reject, with the release
+/// that introduced them.
+const GATED_FLAGS: [(&str, (u32, u32, u32)); 8] = [
+    ("--info=", (3, 1, 0)),
+    ("--outbuf=", (3, 1, 0)),
+    ("--atimes", (3, 2, 0)),
+    ("--crtimes", (3, 2, 0)),
+    ("--open-noatime", (3, 2, 0)),
+    ("--copy-as", (3, 2, 0)),
+    ("--delete-delay", (3, 0, 0)),
+    ("--skip-compress", (3, 0, 0)),
+];
+
+/// Minimum rsync version for `flag`, if it is newer than 2.6.9.
+pub fn required_version(flag: &str) -> Option<(u32, u32, u32)> {
+    GATED_FLAGS
+        .iter()
+        .find(|(prefix, _)| flag.starts_with(prefix))
+        .map(|(_, version)| *version)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RsyncVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    pub protocol: u32,
+}
+
+impl RsyncVersion {
+    /// Parses the first line of `rsync --version`, e.g.
+    /// `rsync  version 3.2.7  protocol version 31`.
+    pub fn parse(output: &str) -> Option<RsyncVersion> {
+        lazy_static! {
+            static ref RE: Regex =
+                Regex::new(r"version\s+v?(\d+)\.(\d+)\.(\d+)\S*\s+protocol version (\d+)").unwrap();
+        }
+
+        let caps = RE.captures(output)?;
+        Some(RsyncVersion {
+            major: caps[1].parse().ok()?,
+            minor: caps[2].parse().ok()?,
+            patch: caps[3].parse().ok()?,
+            protocol: caps[4].parse().ok()?,
+        })
+    }
+
+    pub fn detect(binary: &str) -> Option<RsyncVersion> {
+        let output = Command::new(binary).arg("--version").output().ok()?;
+        RsyncVersion::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    pub fn supports(&self, flag: &str) -> bool {
+        required_version(flag).is_none_or(|x| (self.major, self.minor, self.patch) >= x)
+    }
+}
+
+/// Whether `flag` can be used, assuming a recent rsync when the version is unknown.
+pub fn flag_supported(version: Option<&RsyncVersion>, flag: &str) -> bool {
+    version.is_none_or(|x| x.supports(flag))
+}
+
+/// Tooltip for controls that are disabled because of `flag`.
+pub fn requires_text(flag: &str) -> String {
+    match required_version(flag) {
+        Some((major, minor, patch)) => {
+            format!("Requires rsync {}.{}.{} or newer", major, minor, patch)
+        }
+        None => String::new(),
+    }
+}
+
+/// Deepest directory containing every one of `paths`, if they are all absolute.
+/// Blank lines and `#` comments are skipped.
+pub fn common_base<'a>(paths: impl Iterator<Item = &'a str>) -> Option<PathBuf> {
+    let mut base: Option<PathBuf> = None;
+
+    for path in paths.filter(|x| !x.is_empty() && !x.starts_with('#')) {
+        let path = Path::new(path);
+        if !path.is_absolute() {
+            return None;
+        }
+
+        // A file's own directory, so the file itself stays in the list.
+        let dir = path.parent().unwrap_or(path);
+        base = Some(match base {
+            None => dir.to_path_buf(),
+            Some(base) => base
+                .components()
+                .zip(dir.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+
+    base
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_grouped_and_decimal_numbers() {
+        assert_eq!(parse_rsync_number("1234"), Some(1234));
+        assert_eq!(parse_rsync_number("1,234"), Some(1234));
+        assert_eq!(parse_rsync_number("1.234"), Some(1234));
+        assert_eq!(parse_rsync_number("1,234,567"), Some(1234567));
+        // The fraction is dropped, whichever separator is the decimal point.
+        assert_eq!(parse_rsync_number("1,234.5"), Some(1234));
+        assert_eq!(parse_rsync_number("1.234,5"), Some(1234));
+        assert_eq!(parse_rsync_number("12.5"), Some(12));
+        assert_eq!(parse_rsync_number(" 0 "), Some(0));
+        assert_eq!(parse_rsync_number("n/a"), None);
+    }
+
+    fn version(output: &str) -> RsyncVersion {
+        RsyncVersion::parse(output).unwrap()
+    }
+
+    #[test]
+    fn parses_version_output() {
+        let old = version(
+            "rsync  version 2.6.9  protocol version 29\nCopyright (C) 1996-2006 by Andrew Tridgell, Wayne Davison, and others.\n",
+        );
+        assert_eq!(
+            (old.major, old.minor, old.patch, old.protocol),
+            (2, 6, 9, 29)
+        );
+
+        let v313 = version(
+            "rsync  version 3.1.3  protocol version 31\nCopyright (C) 1996-2018 by Andrew Tridgell, Wayne Davison, and others.\n",
+        );
+        assert_eq!(
+            (v313.major, v313.minor, v313.patch, v313.protocol),
+            (3, 1, 3, 31)
+        );
+
+        let v327 = version(
+            "rsync  version 3.2.7  protocol version 31\nCopyright (C) 1996-2022 by Andrew Tridgell, Wayne Davison, and others.\n",
+        );
+        assert_eq!(
+            (v327.major, v327.minor, v327.patch, v327.protocol),
+            (3, 2, 7, 31)
+        );
+
+        assert!(RsyncVersion::parse("openrsync: protocol version 29").is_none());
+        assert!(RsyncVersion::parse("").is_none());
+    }
+
+    #[test]
+    fn gates_flags_on_version() {
+        let old = version("rsync  version 2.6.9  protocol version 29");
+        let v313 = version("rsync  version 3.1.3  protocol version 31");
+        let v327 = version("rsync  version 3.2.7  protocol version 31");
+
+        assert!(!flag_supported(Some(&old), "--info=progress2"));
+        assert!(!flag_supported(Some(&old), "--delete-delay"));
+        assert!(flag_supported(Some(&old), "--delete"));
+
+        assert!(flag_supported(Some(&v313), "--info=progress2"));
+        assert!(flag_supported(Some(&v313), "--delete-delay"));
+        assert!(!flag_supported(Some(&v313), "--atimes"));
+        assert!(!flag_supported(Some(&v313), "--copy-as"));
+
+        assert!(flag_supported(Some(&v327), "--atimes"));
+        assert!(flag_supported(Some(&v327), "--open-noatime"));
+
+        // Unknown versions are assumed to be recent.
+        assert!(flag_supported(None, "--atimes"));
+        assert_eq!(requires_text("--atimes"), "Requires rsync 3.2.0 or newer");
+        assert_eq!(requires_text("-a"), "");
+    }
+
+    #[test]
+    fn expands_human_readable_suffixes() {
+        assert_eq!(parse_human_number("1.23M"), Some(1_230_000));
+        assert_eq!(parse_human_number("456.00K"), Some(456_000));
+        assert_eq!(parse_human_number("1234567"), Some(1_234_567));
+        assert_eq!(parse_human_number("1,234,567"), Some(1_234_567));
+        // Comma as the decimal point.
+        assert_eq!(parse_human_number("1,50G"), Some(1_500_000_000));
+        assert_eq!(parse_human_number("M"), None);
+    }
+
+    #[test]
+    fn estimates_time_left() {
+        // The cap wins only when it is slower than what was observed.
+        assert_eq!(
+            estimate_eta(10_000, Some(1000.0), Some(500.0)),
+            Some((20, true))
+        );
+        assert_eq!(
+            estimate_eta(10_000, Some(1000.0), Some(2000.0)),
+            Some((10, false))
+        );
+
+        assert_eq!(estimate_eta(10_000, Some(3000.0), None), Some((4, false)));
+        assert_eq!(estimate_eta(0, Some(3000.0), None), Some((0, false)));
+
+        // No samples yet: only a cap gives an estimate.
+        assert_eq!(estimate_eta(10_000, None, Some(500.0)), Some((20, true)));
+        assert_eq!(estimate_eta(10_000, None, None), None);
+        assert_eq!(estimate_eta(10_000, Some(0.0), None), None);
+    }
+
+    #[test]
+    fn parses_rsync_3_2_progress_lines() {
+        // From rsync running in a locale with a decimal comma.
+        let done = parse_rsync_progress(
+            "    32,768,000 100%   31,25MB/s    0:00:01 (xfr#3, to-chk=97/120)",
+        )
+        .unwrap();
+        assert_eq!(
+            done,
+            RsyncProgress {
+                bytes_transferred: 32_768_000,
+                percentage: 100,
+                speed: "31,25MB/s".to_string(),
+                estimated_time: "0:00:01".to_string(),
+                xfr_number: Some(3),
+                check_remaining: Some(97),
+                check_total: Some(120),
+                incremental: false,
+            }
+        );
+        assert_eq!(done.checked(), Some((23, 120)));
+
+        let scanning = parse_rsync_progress(
+            "  1.23G  42%  105,20MB/s    0:00:07 (xfr#1204, ir-chk=1022/3100)",
+        )
+        .unwrap();
+        assert_eq!(scanning.bytes_transferred, 1_230_000_000);
+        assert_eq!(scanning.xfr_number, Some(1204));
+        assert!(scanning.incremental);
+        assert_eq!(scanning.checked(), Some((2078, 3100)));
+
+        // Mid-file lines carry no counters, and the ETA can be unknown.
+        let partial = parse_rsync_progress("      4,194,304  12%    4,00MB/s    ??:??:??").unwrap();
+        assert_eq!(partial.estimated_time, "??:??:??");
+        assert_eq!(partial.xfr_number, None);
+        assert_eq!(partial.checked_fraction(), None);
+
+        // rsync before 3.1.
+        let old =
+            parse_rsync_progress("        1024 100%    1,00MB/s    0:00:00 (xfer#1, to-check=0/1)")
+                .unwrap();
+        assert_eq!(old.checked_fraction(), Some(1.0));
+
+        assert_eq!(parse_rsync_progress("sending incremental file list"), None);
+    }
+
+    #[test]
+    fn parses_transfer_rates() {
+        assert_eq!(parse_speed_bps("10.20MB/s"), Some(10.20 * 1024.0 * 1024.0));
+        assert_eq!(parse_speed_bps("1,024kB/s"), Some(1024.0 * 1024.0));
+        assert_eq!(parse_speed_bps("0.00kB/s"), Some(0.0));
+        assert_eq!(parse_speed_bps("1,234.56kB/s"), Some(1234.56 * 1024.0));
+        assert_eq!(parse_speed_bps("512B/s"), Some(512.0));
+        assert_eq!(parse_speed_bps("fast"), None);
+
+        let progress = parse_rsync_progress("     1,048,576  50%   10.20MB/s    0:00:01").unwrap();
+        assert_eq!(
+            parse_speed_bps(&progress.speed),
+            Some(10.20 * 1024.0 * 1024.0)
+        );
+        let progress = parse_rsync_progress("             0   0%    0.00kB/s    0:00:00").unwrap();
+        assert_eq!(parse_speed_bps(&progress.speed), Some(0.0));
+    }
+
+    #[test]
+    fn counts_small_files_without_progress_lines() {
+        let start = Instant::now();
+        let mut bytes = ByteCount::default();
+        let mut speed = None;
+        // 10,000 files of 100 bytes, one every millisecond, none of them big
+        // enough for rsync to print a progress line.
+        for i in 0..10_000 {
+            speed = bytes.next_file(start + Duration::from_millis(i), 100);
+            assert!(speed.is_some());
+        }
+        assert_eq!(bytes.total(), 9_999 * 100);
+        bytes.finish();
+        assert_eq!(bytes.total(), 10_000 * 100);
+
+        // 100 bytes a millisecond over the last five seconds.
+        let speed = speed.unwrap();
+        assert!((speed - 100_000.0).abs() < 100.0, "{speed}");
+    }
+
+    #[test]
+    fn prefers_rsync_speed_while_it_reports_one() {
+        let start = Instant::now();
+        let mut bytes = ByteCount::default();
+        bytes.next_file(start, 1000);
+        bytes.progress(400);
+        bytes.native_progress(start);
+        assert_eq!(bytes.total(), 400);
+
+        // The bigger of the itemized size and the last progress line counts.
+        assert_eq!(bytes.next_file(start + Duration::from_secs(1), 10), None);
+        assert_eq!(bytes.total(), 1000);
+        assert!(
+            bytes
+                .next_file(start + Duration::from_secs(3), 10)
+                .is_some()
+        );
+        assert_eq!(bytes.total(), 1010);
+
+        // A per-file count starting over means the previous file finished.
+        bytes.progress(5000);
+        bytes.progress(20);
+        assert_eq!(bytes.total(), 1010 + 5000 + 20);
+    }
+
+    #[test]
+    fn keeps_spaces_in_itemized_paths() {
+        let line = parse_itemized_line(">f+++++++++ 52340 My Documents/report final.pdf").unwrap();
+        assert_eq!(
+            line,
+            ItemizedLine {
+                action: ItemAction::New,
+                size: 52340,
+                path: "My Documents/report final.pdf".to_string(),
+            }
+        );
+        assert_eq!(shell_quote(&line.path), "'My Documents/report final.pdf'");
+
+        let deleted = parse_itemized_line("*deleting   0 My Documents/old draft.txt").unwrap();
+        assert_eq!(deleted.action, ItemAction::Deleted);
+        assert_eq!(deleted.path, "My Documents/old draft.txt");
+
+        let updated = parse_itemized_line(">f.st...... 10 My Documents/it's here.txt").unwrap();
+        assert_eq!(updated.action, ItemAction::Updated);
+        assert_eq!(
+            shell_quote(&updated.path),
+            r"'My Documents/it'\''s here.txt'"
+        );
+        assert_eq!(shell_quote("plain/path.txt"), "plain/path.txt");
+    }
+}